@@ -0,0 +1,334 @@
+use std::fmt;
+use std::sync::Arc;
+
+use crate::{
+    CapacityPolicy, CustomHashMap, DoublingPolicy, GrowthFactorPolicy, Hashing, DEFAULT_CAPACITY,
+    EMPTY,
+};
+
+/// Highest load factor [`CustomHashMapBuilder::checked_max_load_factor`]
+/// accepts as is. Past it, probe chains grow quickly as the table fills.
+const SAFE_MAX_LOAD_FACTOR: f64 = 0.9;
+
+/// Error returned by [`CustomHashMapBuilder::checked_max_load_factor`] for a
+/// load factor outside `(0.0, 1.0)`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LoadFactorError(pub f64);
+
+impl fmt::Display for LoadFactorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "max load factor {} is not in (0.0, 1.0)", self.0)
+    }
+}
+
+impl std::error::Error for LoadFactorError {}
+
+/// Configures a [`CustomHashMap`] before it is created.
+///
+/// ```
+/// use rust_open_addresing_linear_probing::CustomHashMap;
+///
+/// let map = CustomHashMap::builder()
+///     .capacity(64)
+///     .growth_factor(1.5)
+///     .build();
+/// assert_eq!(map.capacity(), 64);
+/// ```
+#[derive(Debug, Clone)]
+pub struct CustomHashMapBuilder {
+    capacity: usize,
+    growth_factor: f64,
+    max_load_factor: f64,
+    seed: u64,
+    hashing: Hashing,
+    shrink_on_remove: bool,
+    allow_resize: bool,
+    max_probes: usize,
+    capacity_policy: Option<Arc<dyn CapacityPolicy>>,
+}
+
+impl Default for CustomHashMapBuilder {
+    fn default() -> Self {
+        Self {
+            capacity: DEFAULT_CAPACITY,
+            growth_factor: 2.0,
+            max_load_factor: 0.75,
+            seed: 0,
+            hashing: Hashing::Modulo,
+            shrink_on_remove: false,
+            allow_resize: true,
+            max_probes: usize::MAX,
+            capacity_policy: None,
+        }
+    }
+}
+
+impl CustomHashMapBuilder {
+    /// Sets the initial number of slots. Defaults to 8.
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+
+    /// Sets how much the table grows on resize: the new capacity is the old
+    /// one multiplied by `factor`, rounded up, as a [`GrowthFactorPolicy`].
+    /// Defaults to 2.0, the [`DoublingPolicy`]. Ignored once a
+    /// [`capacity_policy`](Self::capacity_policy) is set.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `factor` is not greater than 1.0.
+    pub fn growth_factor(mut self, factor: f64) -> Self {
+        assert!(factor > 1.0, "growth factor must be greater than 1.0");
+        self.growth_factor = factor;
+        self
+    }
+
+    /// Sets the fraction of slots that may hold entries before the table
+    /// grows. Defaults to 0.75.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `factor` is not in `(0.0, 1.0]`.
+    pub fn max_load_factor(mut self, factor: f64) -> Self {
+        assert!(
+            factor > 0.0 && factor <= 1.0,
+            "max load factor must be in (0.0, 1.0]"
+        );
+        self.max_load_factor = factor;
+        self
+    }
+
+    /// Like [`max_load_factor`](Self::max_load_factor), but guards against
+    /// factors that make probe chains degrade towards O(n): a factor of 1.0
+    /// or more, or not above 0.0, is rejected, and one above 0.9 is clamped
+    /// to 0.9, with a `tracing` warning when that feature is enabled.
+    pub fn checked_max_load_factor(mut self, factor: f64) -> Result<Self, LoadFactorError> {
+        if !(factor > 0.0 && factor < 1.0) {
+            return Err(LoadFactorError(factor));
+        }
+        #[cfg(feature = "tracing")]
+        if factor > SAFE_MAX_LOAD_FACTOR {
+            tracing::warn!(factor, "max load factor clamped to 0.9");
+        }
+        self.max_load_factor = factor.min(SAFE_MAX_LOAD_FACTOR);
+        Ok(self)
+    }
+
+    /// Sets the seed mixed into every hash. Defaults to 0, which hashes a key
+    /// to `key % capacity`.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Sets how keys map to buckets. Defaults to [`Hashing::Modulo`].
+    pub fn hashing(mut self, hashing: Hashing) -> Self {
+        self.hashing = hashing;
+        self
+    }
+
+    /// Makes removals halve the table, rehashing the survivors, whenever fewer
+    /// than 15% of its slots stay live. The table never shrinks below its
+    /// [minimum capacity](CustomHashMap::set_min_capacity), 8 by default. Off
+    /// by default. Like [`growth_factor`](Self::growth_factor), this configures
+    /// a [`GrowthFactorPolicy`] and is ignored once a
+    /// [`capacity_policy`](Self::capacity_policy) is set.
+    pub fn shrink_on_remove(mut self, enabled: bool) -> Self {
+        self.shrink_on_remove = enabled;
+        self
+    }
+
+    /// Lets inserts grow the table once it reaches the maximum load factor.
+    /// With resizing disabled, [`CustomHashMap::checked_insert`] returns a
+    /// [`CapacityError`](crate::CapacityError) instead and
+    /// [`CustomHashMap::insert`] panics. The capacity never changes: calls that
+    /// would grow or shrink the table fail, panic or leave it as it is, as
+    /// documented on each. Defaults to `true`.
+    pub fn allow_resize(mut self, enabled: bool) -> Self {
+        self.allow_resize = enabled;
+        self
+    }
+
+    /// Caps how many slots, counting the home bucket, an insert may probe to
+    /// place a new key. A longer chain grows the table instead, or fails
+    /// [`CustomHashMap::checked_insert`] when resizing is disabled. Defaults to
+    /// no limit beyond the capacity.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_probes` is 0.
+    pub fn max_probes(mut self, max_probes: usize) -> Self {
+        assert!(max_probes > 0, "max probes must be at least 1");
+        self.max_probes = max_probes;
+        self
+    }
+
+    /// Hands resize decisions to `policy` instead of the policy described by
+    /// [`growth_factor`](Self::growth_factor) and
+    /// [`shrink_on_remove`](Self::shrink_on_remove).
+    pub fn capacity_policy(mut self, policy: impl CapacityPolicy + 'static) -> Self {
+        self.capacity_policy = Some(Arc::new(policy));
+        self
+    }
+
+    pub fn build(self) -> CustomHashMap {
+        let capacity = self.hashing.round_capacity(self.capacity);
+        let capacity_policy = self.capacity_policy.unwrap_or_else(|| {
+            if self.growth_factor == 2.0 && !self.shrink_on_remove {
+                Arc::new(DoublingPolicy)
+            } else {
+                Arc::new(GrowthFactorPolicy {
+                    factor: self.growth_factor,
+                    shrink_when_sparse: self.shrink_on_remove,
+                })
+            }
+        });
+        CustomHashMap {
+            ctrl: vec![EMPTY; capacity],
+            slots: vec![(0, 0); capacity],
+            size: 0,
+            deleted: 0,
+            capacity,
+            requested_capacity: self.capacity,
+            max_load_factor: self.max_load_factor,
+            seed: self.seed,
+            hashing: self.hashing,
+            allow_resize: self.allow_resize,
+            max_probes: self.max_probes,
+            min_capacity: DEFAULT_CAPACITY,
+            resizes: 0,
+            version: 0,
+            on_resize: None,
+            capacity_policy,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn capacities_while_inserting(mut map: CustomHashMap, count: u8) -> Vec<usize> {
+        let mut capacities = vec![map.capacity()];
+        for key in 0..count {
+            map.insert(key, key);
+            if map.capacity() != *capacities.last().unwrap() {
+                capacities.push(map.capacity());
+            }
+        }
+        for key in 0..count {
+            assert_eq!(map.get(key), Some(&key));
+        }
+        capacities
+    }
+
+    #[test]
+    fn when_default_growth_should_double_capacity() {
+        let map = CustomHashMap::default();
+        assert_eq!(capacities_while_inserting(map, 20), vec![8, 16, 32]);
+    }
+
+    #[test]
+    fn when_growth_factor_smaller_should_grow_in_smaller_steps() {
+        let map = CustomHashMap::builder().growth_factor(1.5).build();
+        assert_eq!(capacities_while_inserting(map, 20), vec![8, 12, 18, 27]);
+    }
+
+    #[test]
+    fn when_zero_capacity_should_grow_on_first_insert() {
+        let map = CustomHashMap::with_capacity(0);
+        assert_eq!(capacities_while_inserting(map, 3), vec![0, 2, 4]);
+    }
+
+    #[test]
+    fn when_fibonacci_hashing_should_round_capacity_to_power_of_two() {
+        let map = CustomHashMap::builder()
+            .capacity(20)
+            .hashing(Hashing::Fibonacci)
+            .build();
+        assert_eq!(map.capacity(), 32);
+    }
+
+    #[test]
+    fn when_fibonacci_hashing_grows_should_keep_power_of_two() {
+        let map = CustomHashMap::builder()
+            .hashing(Hashing::Fibonacci)
+            .growth_factor(1.5)
+            .build();
+        assert_eq!(capacities_while_inserting(map, 20), vec![8, 16, 32]);
+    }
+
+    #[test]
+    fn when_shrink_on_remove_should_shrink_and_keep_survivors() {
+        let mut map = CustomHashMap::builder().shrink_on_remove(true).build();
+        for key in 0..100 {
+            map.insert(key, key);
+        }
+        let grown = map.capacity();
+        for key in 5..100 {
+            map.remove(key);
+        }
+        assert_eq!((grown, map.capacity()), (256, 32));
+        for key in 0..5 {
+            assert_eq!(map.get(key), Some(&key));
+        }
+    }
+
+    #[test]
+    fn when_shrink_on_remove_should_never_go_below_default_capacity() {
+        let mut map = CustomHashMap::builder()
+            .capacity(4)
+            .shrink_on_remove(true)
+            .build();
+        map.insert(1, 10);
+        map.remove(1);
+        assert_eq!(map.capacity(), 4);
+    }
+
+    #[test]
+    fn when_shrink_disabled_should_keep_capacity() {
+        let mut map = CustomHashMap::default();
+        for key in 0..100 {
+            map.insert(key, key);
+        }
+        for key in 0..100 {
+            map.remove(key);
+        }
+        assert_eq!(map.capacity(), 256);
+    }
+
+    #[test]
+    #[should_panic(expected = "growth factor must be greater than 1.0")]
+    fn when_growth_factor_not_above_one_should_panic() {
+        CustomHashMap::builder().growth_factor(1.0);
+    }
+
+    #[test]
+    fn when_checked_max_load_factor_above_safe_ceiling_should_clamp() {
+        let builder = CustomHashMap::builder()
+            .checked_max_load_factor(0.95)
+            .unwrap();
+        assert_eq!(builder.max_load_factor, 0.9);
+        let builder = builder.checked_max_load_factor(0.8).unwrap();
+        assert_eq!(builder.max_load_factor, 0.8);
+    }
+
+    #[test]
+    fn when_checked_max_load_factor_reaches_one_should_reject() {
+        let result = CustomHashMap::builder().checked_max_load_factor(1.0);
+        assert_eq!(result.unwrap_err(), LoadFactorError(1.0));
+        assert!(CustomHashMap::builder()
+            .checked_max_load_factor(0.0)
+            .is_err());
+        assert!(CustomHashMap::builder()
+            .checked_max_load_factor(f64::NAN)
+            .is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "max load factor must be in (0.0, 1.0]")]
+    fn when_max_load_factor_above_one_should_panic() {
+        CustomHashMap::builder().max_load_factor(1.5);
+    }
+}
@@ -0,0 +1,133 @@
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use crate::CustomHashMap;
+
+/// A [`CustomHashMap`] behind a [`RwLock`], for sharing between threads.
+///
+/// Lookups take the read lock and can run in parallel; inserts and removals
+/// take the write lock. Values are returned by copy so no reference outlives
+/// the lock.
+#[derive(Debug, Default)]
+pub struct ConcurrentHashMap {
+    map: RwLock<CustomHashMap>,
+}
+
+impl ConcurrentHashMap {
+    /// Creates an empty map with the default capacity.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Inserts a key-value pair into the map, returning the old value if the
+    /// key was present.
+    pub fn insert(&self, key: u8, value: u8) -> Option<u8> {
+        self.write().insert(key, value)
+    }
+
+    /// Returns a copy of the value corresponding to the key.
+    pub fn get(&self, key: u8) -> Option<u8> {
+        self.read().get(key).copied()
+    }
+
+    /// Removes a key from the map, returning the value at the key if the key
+    /// was previously in the map.
+    pub fn remove(&self, key: u8) -> Option<u8> {
+        self.write().remove(key)
+    }
+
+    /// Returns the number of elements in the map.
+    pub fn len(&self) -> usize {
+        self.read().len()
+    }
+
+    /// Returns `true` if the map contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.read().is_empty()
+    }
+
+    /// Unwraps the map.
+    pub fn into_inner(self) -> CustomHashMap {
+        self.map.into_inner().expect("map lock poisoned")
+    }
+
+    fn read(&self) -> RwLockReadGuard<'_, CustomHashMap> {
+        self.map.read().expect("map lock poisoned")
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, CustomHashMap> {
+        self.map.write().expect("map lock poisoned")
+    }
+}
+
+impl From<CustomHashMap> for ConcurrentHashMap {
+    fn from(map: CustomHashMap) -> Self {
+        Self {
+            map: RwLock::new(map),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn when_shared_should_be_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<ConcurrentHashMap>();
+    }
+
+    #[test]
+    fn when_readers_race_writers_should_only_see_written_values() {
+        let map = ConcurrentHashMap::new();
+        thread::scope(|scope| {
+            for writer in 0..4u8 {
+                let map = &map;
+                scope.spawn(move || {
+                    for key in (writer..=255).step_by(4) {
+                        map.insert(key, !key);
+                    }
+                });
+            }
+            for _ in 0..4 {
+                let map = &map;
+                scope.spawn(move || {
+                    for _ in 0..8 {
+                        for key in 0..=255 {
+                            let value = map.get(key);
+                            assert!(value.is_none() || value == Some(!key));
+                        }
+                    }
+                });
+            }
+        });
+        assert_eq!(map.len(), 256);
+        for key in 0..=255 {
+            assert_eq!(map.get(key), Some(!key));
+        }
+    }
+
+    #[test]
+    fn when_writers_remove_concurrently_should_keep_len_consistent() {
+        let map = ConcurrentHashMap::from(CustomHashMap::with_capacity(512));
+        for key in 0..=255 {
+            map.insert(key, key);
+        }
+        thread::scope(|scope| {
+            for writer in 0..4u8 {
+                let map = &map;
+                scope.spawn(move || {
+                    for key in (writer..=255).step_by(8) {
+                        assert_eq!(map.remove(key), Some(key));
+                    }
+                });
+            }
+        });
+        assert_eq!(map.len(), 128);
+        let map = map.into_inner();
+        map.validate();
+        assert!((0..=255).all(|key| map.get(key).is_some() == (key % 8 >= 4)));
+    }
+}
@@ -0,0 +1,438 @@
+use crate::{is_full, CustomHashMap};
+
+/// A view into a single slot of a [`CustomHashMap`], returned by
+/// [`CustomHashMap::entry`].
+#[derive(Debug)]
+pub enum Entry<'a> {
+    Occupied(OccupiedEntry<'a>),
+    Vacant(VacantEntry<'a>),
+}
+
+/// An entry whose key is present in the map.
+#[derive(Debug)]
+pub struct OccupiedEntry<'a> {
+    pub(crate) map: &'a mut CustomHashMap,
+    pub(crate) index: usize,
+}
+
+/// An entry whose key is absent from the map.
+#[derive(Debug)]
+pub struct VacantEntry<'a> {
+    pub(crate) map: &'a mut CustomHashMap,
+    pub(crate) key: u8,
+}
+
+/// A builder for a [`RawEntryMut`], returned by
+/// [`CustomHashMap::raw_entry_mut`].
+#[derive(Debug)]
+pub struct RawEntryBuilderMut<'a> {
+    pub(crate) map: &'a mut CustomHashMap,
+    pub(crate) hash: usize,
+}
+
+/// A view into a single slot of a [`CustomHashMap`], found from a
+/// precomputed hash.
+#[derive(Debug)]
+pub enum RawEntryMut<'a> {
+    Occupied(OccupiedEntry<'a>),
+    Vacant(RawVacantEntryMut<'a>),
+}
+
+/// A raw entry for which no matching key was found.
+#[derive(Debug)]
+pub struct RawVacantEntryMut<'a> {
+    map: &'a mut CustomHashMap,
+}
+
+impl<'a> Entry<'a> {
+    /// Returns the key this entry was looked up with.
+    pub fn key(&self) -> &u8 {
+        match self {
+            Entry::Occupied(entry) => entry.key(),
+            Entry::Vacant(entry) => entry.key(),
+        }
+    }
+
+    /// Inserts `default` if the key is absent, and returns a mutable reference
+    /// to the value in either case.
+    pub fn or_insert(self, default: u8) -> &'a mut u8 {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default),
+        }
+    }
+
+    /// Inserts the result of `f` if the key is absent, passing it the map's
+    /// length before the insert, and returns a mutable reference to the value
+    /// in either case.
+    pub fn or_insert_with_len(self, f: impl FnOnce(usize) -> u8) -> &'a mut u8 {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let len = entry.map.len();
+                entry.insert(f(len))
+            }
+        }
+    }
+
+    /// Inserts `default` if the key is absent, and returns a mutable reference
+    /// to the value along with whether a new entry was created.
+    pub fn or_insert_tracked(self, default: u8) -> (&'a mut u8, bool) {
+        match self {
+            Entry::Occupied(entry) => (entry.into_mut(), false),
+            Entry::Vacant(entry) => (entry.insert(default), true),
+        }
+    }
+
+    /// Calls `f` and inserts its value if the key is absent, and returns a
+    /// mutable reference to the value in either case.
+    ///
+    /// An error from `f` is returned as is, and the map is left unchanged.
+    pub fn or_try_insert_with<E>(self, f: impl FnOnce() -> Result<u8, E>) -> Result<&'a mut u8, E> {
+        match self {
+            Entry::Occupied(entry) => Ok(entry.into_mut()),
+            Entry::Vacant(entry) => Ok(entry.insert(f()?)),
+        }
+    }
+}
+
+impl<'a> RawEntryBuilderMut<'a> {
+    /// Looks up `key` on the probe chain of the precomputed hash.
+    pub fn from_key(self, key: u8) -> RawEntryMut<'a> {
+        self.from_key_by(|current| current == key)
+    }
+
+    /// Looks up the first key on the probe chain of the precomputed hash that
+    /// satisfies `eq`.
+    pub fn from_key_by(self, eq: impl FnMut(u8) -> bool) -> RawEntryMut<'a> {
+        match self.map.find_by_hash(self.hash, eq) {
+            Some(index) => RawEntryMut::Occupied(OccupiedEntry {
+                map: self.map,
+                index,
+            }),
+            None => RawEntryMut::Vacant(RawVacantEntryMut { map: self.map }),
+        }
+    }
+}
+
+impl<'a> RawEntryMut<'a> {
+    /// Inserts `key` with `default` if no entry was found, and returns a
+    /// mutable reference to the value in either case.
+    pub fn or_insert(self, key: u8, default: u8) -> &'a mut u8 {
+        match self {
+            RawEntryMut::Occupied(entry) => entry.into_mut(),
+            RawEntryMut::Vacant(entry) => entry.insert(key, default),
+        }
+    }
+}
+
+impl<'a> RawVacantEntryMut<'a> {
+    /// Inserts `key` with `value` and returns a mutable reference to the
+    /// stored value.
+    ///
+    /// The key is placed by its own hash, not the one the entry was looked
+    /// up with: the insert may grow the table, which moves every probe chain,
+    /// and the key must stay reachable through [`CustomHashMap::get`]. If a
+    /// predicate passed to [`RawEntryBuilderMut::from_key_by`] missed `key`
+    /// although it is in the map, its value is overwritten rather than the
+    /// key stored twice.
+    pub fn insert(self, key: u8, value: u8) -> &'a mut u8 {
+        let index = self.map.insert_slot(key);
+        if is_full(self.map.ctrl[index]) {
+            self.map.slots[index].1 = value;
+        } else {
+            self.map.occupy(index, key, value);
+        }
+        &mut self.map.slots[index].1
+    }
+}
+
+impl<'a> OccupiedEntry<'a> {
+    /// Returns the key stored in the entry.
+    pub fn key(&self) -> &u8 {
+        &self.map.slots[self.index].0
+    }
+
+    /// Returns a reference to the entry's value.
+    pub fn get(&self) -> &u8 {
+        &self.map.slots[self.index].1
+    }
+
+    /// Returns a mutable reference to the entry's value.
+    pub fn get_mut(&mut self) -> &mut u8 {
+        &mut self.map.slots[self.index].1
+    }
+
+    /// Converts the entry into a mutable reference to its value, bound to the
+    /// map's lifetime.
+    pub fn into_mut(self) -> &'a mut u8 {
+        &mut self.map.slots[self.index].1
+    }
+
+    /// Replaces the entry's value with `value` and returns the old one.
+    pub fn insert(&mut self, value: u8) -> u8 {
+        std::mem::replace(self.get_mut(), value)
+    }
+
+    /// Removes the entry from the map and returns its value.
+    pub fn remove(self) -> u8 {
+        self.remove_entry().1
+    }
+
+    /// Removes the entry from the map and returns its key and value.
+    pub fn remove_entry(self) -> (u8, u8) {
+        self.map.remove_at(self.index)
+    }
+}
+
+impl<'a> VacantEntry<'a> {
+    /// Returns the key that would be inserted.
+    pub fn key(&self) -> &u8 {
+        &self.key
+    }
+
+    /// Inserts the key with `value` and returns a mutable reference to the
+    /// stored value, bound to the map's lifetime.
+    pub fn insert(self, value: u8) -> &'a mut u8 {
+        let index = self.map.insert_slot(self.key);
+        self.map.occupy(index, self.key, value);
+        &mut self.map.slots[index].1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn when_entry_occupied_remove_should_delete_key() {
+        let mut map = CustomHashMap::default();
+        map.insert(1, 10);
+        map.insert(2, 20);
+        match map.entry(1) {
+            Entry::Occupied(entry) => {
+                assert_eq!(entry.remove(), 10);
+            }
+            Entry::Vacant(_) => panic!("key 1 should be present"),
+        }
+        assert_eq!(map.get(1), None);
+        assert_eq!(map.len(), 1);
+    }
+
+    #[test]
+    fn when_entry_occupied_remove_entry_should_return_pair() {
+        let mut map = CustomHashMap::default();
+        map.insert(1, 10);
+        let Entry::Occupied(entry) = map.entry(1) else {
+            panic!("key 1 should be present");
+        };
+        assert_eq!(entry.remove_entry(), (1, 10));
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn when_entry_removes_mid_chain_should_keep_later_keys_reachable() {
+        let mut map = CustomHashMap::default();
+        map.insert(1, 10); // hash: 1
+        map.insert(9, 90); // hash: 1, probes to 2
+        map.insert(17, 170); // hash: 1, probes to 3
+        let Entry::Occupied(entry) = map.entry(9) else {
+            panic!("key 9 should be present");
+        };
+        assert_eq!(entry.remove(), 90);
+        assert_eq!(map.get(17), Some(&170));
+        assert!(matches!(map.entry(17), Entry::Occupied(_)));
+        assert_eq!(map.insert(17, 171), Some(170));
+        assert_eq!(map.len(), 2);
+    }
+
+    #[test]
+    fn when_entry_absent_should_be_vacant() {
+        let mut map = CustomHashMap::default();
+        map.insert(1, 10);
+        assert!(matches!(map.entry(9), Entry::Vacant(_)));
+        assert_eq!(map.len(), 1);
+    }
+
+    #[test]
+    fn when_entry_key_should_return_lookup_key_for_both_variants() {
+        let mut map = CustomHashMap::default();
+        map.insert(1, 10);
+        let occupied = map.entry(1);
+        assert!(matches!(occupied, Entry::Occupied(_)));
+        assert_eq!(occupied.key(), &1);
+        let vacant = map.entry(9);
+        assert!(matches!(vacant, Entry::Vacant(_)));
+        assert_eq!(vacant.key(), &9);
+    }
+
+    #[test]
+    fn when_or_insert_should_insert_only_if_absent() {
+        let mut map = CustomHashMap::default();
+        *map.entry(1).or_insert(10) += 1;
+        *map.entry(1).or_insert(50) += 1;
+        assert_eq!(map.get(1), Some(&12));
+        assert_eq!(map.len(), 1);
+    }
+
+    #[test]
+    fn when_or_try_insert_with_fails_should_leave_map_unchanged() {
+        let mut map = CustomHashMap::default();
+        map.insert(1, 10);
+        let result = map.entry(9).or_try_insert_with(|| Err("no value"));
+        assert_eq!(result, Err("no value"));
+        assert_eq!(map.get(9), None);
+        assert_eq!(map.len(), 1);
+        let result = map.entry(1).or_try_insert_with(|| Err("unused"));
+        assert_eq!(result, Ok(&mut 10));
+    }
+
+    #[test]
+    fn when_or_try_insert_with_succeeds_should_insert() {
+        let mut map = CustomHashMap::default();
+        *map.entry(9).or_try_insert_with(|| Ok::<_, ()>(90)).unwrap() += 1;
+        assert_eq!(map.get(9), Some(&91));
+        assert_eq!(map.len(), 1);
+    }
+
+    #[test]
+    fn when_vacant_insert_should_return_mutable_value() {
+        let mut map = CustomHashMap::default();
+        map.insert(1, 10);
+        match map.entry(9) {
+            Entry::Vacant(entry) => *entry.insert(90) += 1,
+            Entry::Occupied(_) => panic!("key 9 should be absent"),
+        }
+        assert_eq!(map.get(9), Some(&91));
+        assert_eq!(map.len(), 2);
+    }
+
+    #[test]
+    fn when_occupied_insert_should_return_old_value() {
+        let mut map = CustomHashMap::default();
+        map.insert(1, 10);
+        let Entry::Occupied(mut entry) = map.entry(1) else {
+            panic!("key 1 should be present");
+        };
+        assert_eq!(entry.insert(11), 10);
+        assert_eq!(entry.get(), &11);
+        assert_eq!(map.get(1), Some(&11));
+        assert_eq!(map.len(), 1);
+    }
+
+    #[test]
+    fn when_or_insert_tracked_should_report_whether_it_inserted() {
+        let mut map = CustomHashMap::default();
+        map.insert(1, 10);
+        let (value, inserted) = map.entry(9).or_insert_tracked(90);
+        assert_eq!((*value, inserted), (90, true));
+        let (value, inserted) = map.entry(1).or_insert_tracked(50);
+        *value += 1;
+        assert!(!inserted);
+        assert_eq!(map.get(1), Some(&11));
+        assert_eq!(map.len(), 2);
+    }
+
+    #[test]
+    fn when_raw_entry_uses_precomputed_hash_should_insert_and_find() {
+        let mut map = CustomHashMap::default();
+        map.insert(1, 10);
+        let hash = 9; // default seed and modulo hashing: a key's hash is itself
+        let value = map
+            .raw_entry_mut(hash)
+            .from_key_by(|key| key == 9)
+            .or_insert(9, 90);
+        *value += 1;
+        assert_eq!(map.get(9), Some(&91));
+        match map.raw_entry_mut(hash).from_key(9) {
+            RawEntryMut::Occupied(entry) => assert_eq!(entry.remove(), 91),
+            RawEntryMut::Vacant(_) => panic!("key 9 should be present"),
+        }
+        // Keys 1 and 9 share bucket 1; a predicate can match either.
+        let odd = map.raw_entry_mut(hash).from_key_by(|key| key % 2 == 1);
+        assert!(matches!(odd, RawEntryMut::Occupied(entry) if *entry.key() == 1));
+        assert!(matches!(
+            map.raw_entry_mut(hash).from_key(9),
+            RawEntryMut::Vacant(_)
+        ));
+    }
+
+    #[test]
+    fn when_raw_predicate_misses_present_key_should_overwrite_instead_of_duplicating() {
+        let mut map = CustomHashMap::default();
+        map.insert(1, 10);
+        let entry = map.raw_entry_mut(1).from_key_by(|_| false);
+        assert!(matches!(entry, RawEntryMut::Vacant(_)));
+        assert_eq!(entry.or_insert(1, 11), &mut 11);
+        assert_eq!(map.len(), 1);
+        assert_eq!(map.get(1), Some(&11));
+        map.validate();
+    }
+
+    #[test]
+    fn when_or_insert_with_len_should_assign_sequential_ids() {
+        let mut map = CustomHashMap::default();
+        for key in [40, 7, 40, 19, 7, 3] {
+            map.entry(key).or_insert_with_len(|len| len as u8);
+        }
+        assert_eq!(
+            map.get_all(&[40, 7, 19, 3]),
+            vec![Some(0), Some(1), Some(2), Some(3)]
+        );
+    }
+
+    #[test]
+    fn when_entry_at_resize_boundary_should_insert_into_grown_table() {
+        let mut map = CustomHashMap::default();
+        for key in 0..6 {
+            map.insert(key, key);
+        }
+        assert_eq!(map.capacity(), 8);
+        let entry = map.entry(14); // hash: 6 before growing
+        assert!(matches!(entry, Entry::Vacant(_)));
+        *entry.or_insert(140) += 1;
+        assert!(map.capacity() > 8);
+        assert_eq!(map.resize_count(), 1);
+        assert_eq!(map.get(14), Some(&141));
+        assert_eq!(map.len(), 7);
+        map.validate();
+    }
+
+    #[test]
+    fn when_vacant_entry_at_resize_boundary_is_not_inserted_should_leave_table_alone() {
+        let mut map = CustomHashMap::default();
+        for key in 0..6 {
+            map.insert(key, key);
+        }
+        let result = map.entry(14).or_try_insert_with(|| Err("no value"));
+        assert_eq!(result, Err("no value"));
+        assert_eq!(map.capacity(), 8);
+        assert_eq!(map.resize_count(), 0);
+        assert_eq!(map.len(), 6);
+    }
+
+    #[test]
+    fn when_vacant_entry_on_full_fixed_map_is_not_inserted_should_not_panic() {
+        let mut map = CustomHashMap::builder()
+            .capacity(4)
+            .max_load_factor(1.0)
+            .allow_resize(false)
+            .build();
+        for key in 0..4 {
+            map.insert(key, key);
+        }
+        assert!(matches!(map.entry(9), Entry::Vacant(_)));
+        assert_eq!(map.entry(2).or_insert(0), &mut 2);
+    }
+
+    #[test]
+    fn when_occupied_get_mut_should_update_value() {
+        let mut map = CustomHashMap::default();
+        map.insert(1, 10);
+        if let Entry::Occupied(mut entry) = map.entry(1) {
+            *entry.get_mut() = 11;
+            assert_eq!(entry.get(), &11);
+        }
+        assert_eq!(map.get(1), Some(&11));
+    }
+}
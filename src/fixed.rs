@@ -0,0 +1,177 @@
+use crate::{free_slot, h2, is_full, probe, CapacityError, EMPTY};
+
+/// A linear-probing map whose `N` slots live inline, so it never allocates.
+///
+/// It never resizes either: once every slot is taken, inserting a new key
+/// fails with [`CapacityError`].
+#[derive(Debug)]
+pub struct FixedHashMap<const N: usize> {
+    ctrl: [u8; N],
+    slots: [(u8, u8); N],
+    size: usize,
+    deleted: usize,
+}
+
+impl<const N: usize> Default for FixedHashMap<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> FixedHashMap<N> {
+    pub fn new() -> Self {
+        Self {
+            ctrl: [EMPTY; N],
+            slots: [(0, 0); N],
+            size: 0,
+            deleted: 0,
+        }
+    }
+
+    /// Inserts a key-value pair into the map.
+    ///
+    /// Returns the old value if the key was present, or [`CapacityError`] if
+    /// the key is new and there is no free slot for it.
+    pub fn insert(&mut self, key: u8, value: u8) -> Result<Option<u8>, CapacityError> {
+        let index = self.probe(key, true).ok_or(CapacityError)?;
+        if is_full(self.ctrl[index]) {
+            return Ok(Some(std::mem::replace(&mut self.slots[index].1, value)));
+        }
+        if self.ctrl[index] != EMPTY {
+            self.deleted -= 1;
+        }
+        self.ctrl[index] = h2(key);
+        self.slots[index] = (key, value);
+        self.size += 1;
+        Ok(None)
+    }
+
+    /// Returns a reference to the value corresponding to the key.
+    pub fn get(&self, key: u8) -> Option<&u8> {
+        self.find(key).map(|index| &self.slots[index].1)
+    }
+
+    /// Removes a key from the map, returning the value at the key if the key
+    /// was previously in the map.
+    ///
+    /// Since the table is never rehashed, the slot is only tombstoned when a
+    /// probe chain continues past it. Otherwise it is marked empty, along
+    /// with the tombstones right before it.
+    pub fn remove(&mut self, key: u8) -> Option<u8> {
+        let index = self.find(key)?;
+        free_slot(&mut self.ctrl, index, &mut self.deleted);
+        self.size -= 1;
+        Some(self.slots[index].1)
+    }
+
+    /// Returns the number of elements in the map.
+    pub fn len(&self) -> usize {
+        self.size
+    }
+
+    /// Returns `true` if the map contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.size == 0
+    }
+
+    fn find(&self, key: u8) -> Option<usize> {
+        self.probe(key, false)
+            .filter(|&index| is_full(self.ctrl[index]))
+    }
+
+    fn probe(&self, key: u8, reuse_deleted: bool) -> Option<usize> {
+        if N == 0 {
+            return None;
+        }
+        probe(
+            &self.ctrl,
+            &self.slots,
+            key as usize % N,
+            key,
+            h2(key),
+            reuse_deleted,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DELETED;
+
+    #[test]
+    fn when_new_should_be_empty() {
+        let map = FixedHashMap::<8>::new();
+        assert_eq!(map.len(), 0);
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn when_insert_get_remove_should_behave_like_heap_map() {
+        let mut map = FixedHashMap::<8>::new();
+        assert_eq!(map.insert(1, 10), Ok(None));
+        assert_eq!(map.insert(9, 90), Ok(None)); // hash: 1, probes to 2
+        assert_eq!(map.insert(1, 11), Ok(Some(10)));
+        assert_eq!(map.remove(1), Some(11));
+        assert_eq!(map.get(9), Some(&90)); // found through the tombstone
+        assert_eq!(map.len(), 1);
+    }
+
+    #[test]
+    fn when_full_should_return_capacity_error() {
+        let mut map = FixedHashMap::<8>::new();
+        for i in 0..8 {
+            assert_eq!(map.insert(i, i * 10), Ok(None));
+        }
+        assert_eq!(map.insert(8, 80), Err(CapacityError));
+        assert_eq!(map.insert(3, 33), Ok(Some(30))); // updates still work
+        assert_eq!(map.len(), 8);
+        assert_eq!(map.get(8), None);
+    }
+
+    #[test]
+    fn when_full_then_removed_should_accept_new_key() {
+        let mut map = FixedHashMap::<8>::new();
+        for i in 0..8 {
+            map.insert(i, i).unwrap();
+        }
+        map.remove(5);
+        assert_eq!(map.insert(13, 130), Ok(None)); // hash: 5, reuses the tombstone
+        assert_eq!(map.get(13), Some(&130));
+    }
+
+    #[test]
+    fn when_keys_churn_should_reclaim_tombstones() {
+        let mut map = FixedHashMap::<16>::new();
+        let pair = |round: u32| {
+            let key = (round * 37 % 256) as u8;
+            [key, key.wrapping_add(16)] // both hash to the same bucket
+        };
+        for round in 0..1000 {
+            for key in pair(round) {
+                map.insert(key, key).unwrap();
+            }
+            if round >= 3 {
+                for key in pair(round - 3) {
+                    assert_eq!(map.remove(key), Some(key));
+                }
+            }
+            let tombstones = map.ctrl.iter().filter(|&&ctrl| ctrl == DELETED).count();
+            assert_eq!(tombstones, map.deleted);
+            // A missed lookup still stops at an empty slot.
+            assert!(
+                map.ctrl.contains(&EMPTY),
+                "round {round}: no empty slot left"
+            );
+        }
+        assert_eq!(map.len(), 6);
+        assert_eq!(map.get(1), None);
+    }
+
+    #[test]
+    fn when_zero_capacity_should_reject_inserts() {
+        let mut map = FixedHashMap::<0>::new();
+        assert_eq!(map.insert(1, 10), Err(CapacityError));
+        assert_eq!(map.get(1), None);
+    }
+}
@@ -0,0 +1,116 @@
+use crate::{h2, CustomHashMap, EMPTY};
+
+/// A read-only map produced by [`CustomHashMap::freeze`].
+///
+/// Its table holds no tombstones and is packed to a load of at most 7/8, so a
+/// lookup only ever stops at its key or at an empty slot.
+#[derive(Debug)]
+pub struct FrozenHashMap {
+    pub(crate) map: CustomHashMap,
+}
+
+impl FrozenHashMap {
+    /// Returns a reference to the value corresponding to the key.
+    pub fn get(&self, key: u8) -> Option<&u8> {
+        let map = &self.map;
+        if map.capacity == 0 {
+            return None;
+        }
+        let home = map.hash(key);
+        let tag = h2(key);
+        for distance in 0..map.capacity {
+            let index = (home + distance) % map.capacity;
+            match map.ctrl[index] {
+                EMPTY => return None,
+                ctrl if ctrl == tag && map.slots[index].0 == key => {
+                    return Some(&map.slots[index].1);
+                }
+                _ => {}
+            }
+        }
+        None
+    }
+
+    /// Returns `true` if the map contains a value for the key.
+    pub fn contains_key(&self, key: u8) -> bool {
+        self.get(key).is_some()
+    }
+
+    /// Returns the number of elements in the map.
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns `true` if the map contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Returns the number of slots in the table.
+    pub fn capacity(&self) -> usize {
+        self.map.capacity()
+    }
+
+    /// Iterates over copies of the entries, in physical slot order.
+    pub fn iter(&self) -> impl Iterator<Item = (u8, u8)> + '_ {
+        self.map.live_entries()
+    }
+
+    /// Iterates over the keys, in physical slot order.
+    pub fn keys(&self) -> impl Iterator<Item = u8> + '_ {
+        self.iter().map(|(key, _)| key)
+    }
+
+    /// Iterates over the values, in physical slot order.
+    pub fn values(&self) -> impl Iterator<Item = u8> + '_ {
+        self.iter().map(|(_, value)| value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn when_frozen_should_answer_lookups_like_source() {
+        let mut map = CustomHashMap::default();
+        for key in 0..60 {
+            map.insert(key, key.wrapping_mul(7));
+        }
+        for key in (0..60).step_by(4) {
+            map.remove(key);
+        }
+        let expected: Vec<Option<u8>> = (0..=u8::MAX).map(|key| map.get(key).copied()).collect();
+        let frozen = map.freeze();
+        for key in 0..=u8::MAX {
+            assert_eq!(frozen.get(key).copied(), expected[key as usize]);
+            assert_eq!(frozen.contains_key(key), expected[key as usize].is_some());
+        }
+        assert_eq!(frozen.len(), 45);
+        assert_eq!(frozen.iter().count(), 45);
+        assert!(frozen
+            .iter()
+            .all(|(key, value)| value == key.wrapping_mul(7)));
+    }
+
+    #[test]
+    fn when_frozen_should_have_no_tombstones_and_tight_capacity() {
+        let mut map = CustomHashMap::with_capacity(256);
+        for key in 0..20 {
+            map.insert(key, key);
+        }
+        map.remove(3);
+        let frozen = map.freeze();
+        assert_eq!(frozen.map.deleted, 0);
+        assert!(frozen.map.ctrl.contains(&EMPTY));
+        assert_eq!(frozen.capacity(), 22);
+        assert!(frozen.keys().all(|key| key != 3));
+    }
+
+    #[test]
+    fn when_empty_map_frozen_should_find_nothing() {
+        let frozen = CustomHashMap::with_capacity(0).freeze();
+        assert!(frozen.is_empty());
+        assert_eq!(frozen.get(1), None);
+    }
+}
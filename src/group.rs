@@ -0,0 +1,242 @@
+//! Group-wise scanning of control bytes.
+//!
+//! A [`Group`] is [`WIDTH`] consecutive control bytes compared in one go. With
+//! the `simd` feature on x86_64 the comparisons use SSE2, which is part of the
+//! x86_64 baseline and so is detected at compile time; every other build uses a
+//! scalar loop that produces the same [`BitMask`]. There is no NEON path, so
+//! aarch64 builds use the scalar loop even with `simd` enabled.
+
+pub(crate) const WIDTH: usize = 16;
+
+/// One bit per control byte of a group: bit `i` is set when byte `i` matched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct BitMask(pub(crate) u16);
+
+impl BitMask {
+    /// Index of the first matching byte, if any.
+    pub(crate) fn lowest(self) -> Option<usize> {
+        if self.0 == 0 {
+            None
+        } else {
+            Some(self.0.trailing_zeros() as usize)
+        }
+    }
+
+    /// Keeps only the matches strictly before byte `index`.
+    pub(crate) fn below(self, index: usize) -> Self {
+        Self(self.0 & ((1u32 << index) - 1) as u16)
+    }
+}
+
+impl Iterator for BitMask {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        let index = self.lowest()?;
+        self.0 &= self.0 - 1;
+        Some(index)
+    }
+}
+
+#[cfg(all(
+    feature = "simd",
+    target_arch = "x86_64",
+    target_feature = "sse2",
+    not(test)
+))]
+pub(crate) use sse2::Group;
+
+#[cfg(all(
+    feature = "simd",
+    target_arch = "x86_64",
+    target_feature = "sse2",
+    test
+))]
+pub(crate) use dispatch::Group;
+
+#[cfg(not(all(feature = "simd", target_arch = "x86_64", target_feature = "sse2")))]
+pub(crate) use scalar::Group;
+
+#[cfg_attr(
+    all(feature = "simd", target_arch = "x86_64", target_feature = "sse2"),
+    cfg(test)
+)]
+mod scalar {
+    use super::{BitMask, WIDTH};
+
+    pub(crate) struct Group([u8; WIDTH]);
+
+    impl Group {
+        pub(crate) fn load(ctrl: &[u8; WIDTH]) -> Self {
+            Self(*ctrl)
+        }
+
+        pub(crate) fn match_byte(&self, byte: u8) -> BitMask {
+            self.mask(|ctrl| ctrl == byte)
+        }
+
+        /// Matches every byte with the top bit set, i.e. `EMPTY` or `DELETED`.
+        pub(crate) fn match_empty_or_deleted(&self) -> BitMask {
+            self.mask(|ctrl| ctrl & 0b1000_0000 != 0)
+        }
+
+        fn mask(&self, matches: impl Fn(u8) -> bool) -> BitMask {
+            let mut bits = 0;
+            for (index, &ctrl) in self.0.iter().enumerate() {
+                if matches(ctrl) {
+                    bits |= 1 << index;
+                }
+            }
+            BitMask(bits)
+        }
+    }
+}
+
+#[cfg(all(feature = "simd", target_arch = "x86_64", target_feature = "sse2"))]
+mod sse2 {
+    use super::{BitMask, WIDTH};
+    use core::arch::x86_64::{
+        __m128i, _mm_cmpeq_epi8, _mm_loadu_si128, _mm_movemask_epi8, _mm_set1_epi8,
+    };
+
+    pub(crate) struct Group(__m128i);
+
+    impl Group {
+        pub(crate) fn load(ctrl: &[u8; WIDTH]) -> Self {
+            // SAFETY: `ctrl` is exactly 16 readable bytes and the load is unaligned.
+            Self(unsafe { _mm_loadu_si128(ctrl.as_ptr().cast()) })
+        }
+
+        pub(crate) fn match_byte(&self, byte: u8) -> BitMask {
+            // SAFETY: SSE2 is part of the x86_64 baseline and enabled by the cfg above.
+            let bits = unsafe {
+                let matches = _mm_cmpeq_epi8(self.0, _mm_set1_epi8(byte as i8));
+                _mm_movemask_epi8(matches)
+            };
+            BitMask(bits as u16)
+        }
+
+        /// Matches every byte with the top bit set, i.e. `EMPTY` or `DELETED`.
+        pub(crate) fn match_empty_or_deleted(&self) -> BitMask {
+            // SAFETY: SSE2 is part of the x86_64 baseline and enabled by the cfg above.
+            BitMask(unsafe { _mm_movemask_epi8(self.0) } as u16)
+        }
+    }
+}
+
+#[cfg(all(
+    feature = "simd",
+    target_arch = "x86_64",
+    target_feature = "sse2",
+    test
+))]
+thread_local! {
+    static FORCE_SCALAR: std::cell::Cell<bool> = const { std::cell::Cell::new(false) };
+}
+
+/// Runs `f` with every group load on this thread using the scalar loop, so
+/// tests can replay one operation sequence on both paths of a `simd` build.
+#[cfg(all(
+    feature = "simd",
+    target_arch = "x86_64",
+    target_feature = "sse2",
+    test
+))]
+pub(crate) fn with_scalar_groups<R>(f: impl FnOnce() -> R) -> R {
+    FORCE_SCALAR.with(|forced| forced.set(true));
+    let result = f();
+    FORCE_SCALAR.with(|forced| forced.set(false));
+    result
+}
+
+/// In `simd` test builds, picks the SSE2 or scalar group at load time
+/// depending on [`with_scalar_groups`].
+#[cfg(all(
+    feature = "simd",
+    target_arch = "x86_64",
+    target_feature = "sse2",
+    test
+))]
+mod dispatch {
+    use super::{scalar, sse2, BitMask, FORCE_SCALAR, WIDTH};
+
+    pub(crate) enum Group {
+        Sse2(sse2::Group),
+        Scalar(scalar::Group),
+    }
+
+    impl Group {
+        pub(crate) fn load(ctrl: &[u8; WIDTH]) -> Self {
+            if FORCE_SCALAR.with(|forced| forced.get()) {
+                Group::Scalar(scalar::Group::load(ctrl))
+            } else {
+                Group::Sse2(sse2::Group::load(ctrl))
+            }
+        }
+
+        pub(crate) fn match_byte(&self, byte: u8) -> BitMask {
+            match self {
+                Group::Sse2(group) => group.match_byte(byte),
+                Group::Scalar(group) => group.match_byte(byte),
+            }
+        }
+
+        pub(crate) fn match_empty_or_deleted(&self) -> BitMask {
+            match self {
+                Group::Sse2(group) => group.match_empty_or_deleted(),
+                Group::Scalar(group) => group.match_empty_or_deleted(),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn when_iterating_bitmask_should_yield_set_bits_in_order() {
+        let mask = BitMask(0b1000_0000_0010_0101);
+        assert_eq!(mask.collect::<Vec<_>>(), vec![0, 2, 5, 15]);
+        assert_eq!(mask.below(5).collect::<Vec<_>>(), vec![0, 2]);
+        assert_eq!(mask.below(WIDTH), mask);
+        assert_eq!(BitMask(0).lowest(), None);
+    }
+
+    #[test]
+    fn when_matching_group_should_report_each_byte() {
+        let mut ctrl = [0xFF; WIDTH];
+        ctrl[1] = 0x80;
+        ctrl[3] = 0x05;
+        ctrl[9] = 0x05;
+        let group = Group::load(&ctrl);
+        assert_eq!(group.match_byte(0x05), BitMask(1 << 3 | 1 << 9));
+        assert_eq!(group.match_byte(0x80), BitMask(1 << 1));
+        assert_eq!(group.match_empty_or_deleted(), BitMask(!(1 << 3 | 1 << 9)));
+    }
+
+    #[cfg(all(feature = "simd", target_arch = "x86_64", target_feature = "sse2"))]
+    #[test]
+    fn when_random_groups_should_match_scalar_masks() {
+        let mut rng = crate::tests::XorShift(0x9E37_79B9);
+        for _ in 0..10_000 {
+            let mut ctrl = [0; WIDTH];
+            for byte in &mut ctrl {
+                *byte = match rng.next() % 4 {
+                    0 => 0xFF,
+                    1 => 0x80,
+                    _ => rng.next_u8(8),
+                };
+            }
+            let simd = sse2::Group::load(&ctrl);
+            let scalar = scalar::Group::load(&ctrl);
+            for byte in [0xFF, 0x80, 0, 3, 7] {
+                assert_eq!(simd.match_byte(byte), scalar.match_byte(byte));
+            }
+            assert_eq!(
+                simd.match_empty_or_deleted(),
+                scalar.match_empty_or_deleted()
+            );
+        }
+    }
+}
@@ -1,35 +1,239 @@
+mod builder;
+mod concurrent;
+mod entry;
+mod fixed;
+mod frozen;
+mod group;
+mod policy;
+mod spill;
+mod ttl;
+mod wide;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::sync::Arc;
+
+use group::{Group, WIDTH};
+
+pub use builder::{CustomHashMapBuilder, LoadFactorError};
+pub use concurrent::ConcurrentHashMap;
+pub use entry::{
+    Entry, OccupiedEntry, RawEntryBuilderMut, RawEntryMut, RawVacantEntryMut, VacantEntry,
+};
+pub use fixed::FixedHashMap;
+pub use frozen::FrozenHashMap;
+pub use policy::{CapacityPolicy, DoublingPolicy, GrowthFactorPolicy};
+pub use spill::{Backend, FileBackend, SpillingHashMap};
+pub use ttl::{Clock, SystemClock, TtlHashMap};
+pub use wide::{CustomHashMapU16, CustomHashMapU32, CustomHashMapU64};
+
+/// Emits a `tracing` event at `$level` when the `tracing` feature is enabled,
+/// and compiles to nothing otherwise.
+macro_rules! trace_event {
+    ($level:ident, $($arg:tt)*) => {
+        #[cfg(feature = "tracing")]
+        tracing::$level!($($arg)*);
+    };
+}
+
+/// Capacity of a default map, and the default floor shrinking stops at.
+const DEFAULT_CAPACITY: usize = 8;
+
+/// Live load below which a [`GrowthFactorPolicy`] with `shrink_when_sparse`
+/// halves the table.
+const SHRINK_LOAD: f64 = 0.15;
+
+/// Probe length, counting the home bucket, past which an insert is reported as
+/// a long probe chain.
+#[cfg(feature = "tracing")]
+const LONG_PROBE_CHAIN: usize = 8;
+
+/// Control byte of a slot that has never held an entry.
+const EMPTY: u8 = 0b1111_1111;
+/// Control byte of a slot whose entry was removed (a tombstone).
+const DELETED: u8 = 0b1000_0000;
+
+/// Control byte stored for an occupied slot holding `key`: its low seven bits,
+/// so the top bit is always clear and never collides with `EMPTY`/`DELETED`.
+fn h2(key: u8) -> u8 {
+    key & 0b0111_1111
+}
+
+fn is_full(ctrl: u8) -> bool {
+    ctrl & 0b1000_0000 == 0
+}
+
+/// Error returned when an insert needs a free slot but none is left.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CapacityError;
+
+impl fmt::Display for CapacityError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("map is at capacity")
+    }
+}
+
+impl std::error::Error for CapacityError {}
+
+/// Error returned by [`CustomHashMap::from_raw_parts`] for parts that do not
+/// describe a valid table, naming the first broken invariant.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawPartsError(pub String);
+
+impl fmt::Display for RawPartsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid raw parts: {}", self.0)
+    }
+}
+
+impl std::error::Error for RawPartsError {}
+
+/// How a key is mapped to its home bucket.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Hashing {
+    /// `key % capacity`.
+    #[default]
+    Modulo,
+    /// Multiplies the key by the 64-bit golden-ratio constant and keeps the
+    /// top bits, which spreads dense or strided key ranges evenly. Capacities
+    /// are rounded up to a power of two in this mode.
+    Fibonacci,
+}
+
+impl Hashing {
+    fn round_capacity(self, capacity: usize) -> usize {
+        match self {
+            Hashing::Modulo => capacity,
+            Hashing::Fibonacci => capacity.next_power_of_two(),
+        }
+    }
+}
+
+/// A type-level capacity: `CustomHashMap::from(DefaultCapacity::<N>)` creates
+/// a map with `N` slots, for code that threads the capacity through generics.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DefaultCapacity<const N: usize>;
+
+/// The state of one physical slot, as yielded by
+/// [`CustomHashMap::iter_slots`].
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
-enum Slot {
+pub enum SlotView {
     Vacant,
     Deleted,
     Occupied { key: u8, value: u8 },
 }
 
-#[derive(Debug)]
+/// How full a table is, as fractions of its capacity.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Utilization {
+    /// Live entries per slot. A low value means the table could shrink.
+    pub live_load: f64,
+    /// Live entries plus tombstones per slot: the share of slots a probe has to
+    /// step over. A value well above `live_load` means the table could use a
+    /// rehash.
+    pub probe_load: f64,
+}
+
+/// Probe distances of the live entries: how many slots past its home bucket
+/// each entry sits.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ProbeStats {
+    pub max: usize,
+    pub mean: f64,
+}
+
+/// Callback registered with [`CustomHashMap::on_resize`].
+type ResizeHook = Box<dyn FnMut(usize, usize) + Send + Sync>;
+
 pub struct CustomHashMap {
-    entries: Vec<Slot>,
+    ctrl: Vec<u8>,
+    slots: Vec<(u8, u8)>,
     size: usize,
+    deleted: usize,
     capacity: usize,
+    requested_capacity: usize,
+    max_load_factor: f64,
+    seed: u64,
+    hashing: Hashing,
+    allow_resize: bool,
+    max_probes: usize,
+    min_capacity: usize,
+    resizes: usize,
+    version: u64,
+    on_resize: Option<ResizeHook>,
+    capacity_policy: Arc<dyn CapacityPolicy>,
+}
+
+impl fmt::Debug for CustomHashMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CustomHashMap")
+            .field("ctrl", &self.ctrl)
+            .field("slots", &self.slots)
+            .field("size", &self.size)
+            .field("deleted", &self.deleted)
+            .field("capacity", &self.capacity)
+            .field("requested_capacity", &self.requested_capacity)
+            .field("max_load_factor", &self.max_load_factor)
+            .field("seed", &self.seed)
+            .field("hashing", &self.hashing)
+            .field("allow_resize", &self.allow_resize)
+            .field("max_probes", &self.max_probes)
+            .field("min_capacity", &self.min_capacity)
+            .field("resizes", &self.resizes)
+            .field("version", &self.version)
+            .field("capacity_policy", &self.capacity_policy)
+            .finish_non_exhaustive()
+    }
 }
 
 impl Default for CustomHashMap {
+    /// Hashing is unseeded, so two maps given the same operations always end
+    /// up with the same slot layout.
     fn default() -> Self {
-        let default_capacity = 8;
-        Self::with_capacity(default_capacity)
+        Self::builder().build()
     }
 }
 
 impl CustomHashMap {
     pub fn with_capacity(capacity: usize) -> Self {
-        Self {
-            entries: vec![Slot::Vacant; capacity],
-            size: 0,
-            capacity,
-        }
+        Self::builder().capacity(capacity).build()
+    }
+
+    /// Creates a map with `N` slots, chosen at compile time.
+    pub fn with_default_capacity<const N: usize>() -> Self {
+        Self::with_capacity(N)
+    }
+
+    /// Creates a map whose bucket layout depends on `seed`. The seed is mixed
+    /// into the key before it is reduced to a bucket, so keys that collide
+    /// under one seed are scattered under another and callers who cannot
+    /// predict the seed cannot force keys into a single run.
+    ///
+    /// A seed of 0 gives the same layout as [`with_capacity`](Self::with_capacity).
+    pub fn with_seed(capacity: usize, seed: u64) -> Self {
+        Self::builder().capacity(capacity).seed(seed).build()
+    }
+
+    /// Returns a builder for configuring a map before creating it.
+    pub fn builder() -> CustomHashMapBuilder {
+        CustomHashMapBuilder::default()
     }
 
     fn hash(&self, key: u8) -> usize {
-        (key as usize) % self.capacity
+        let mixed = mix(key, self.seed);
+        match self.hashing {
+            Hashing::Modulo => (mixed % self.capacity as u64) as usize,
+            Hashing::Fibonacci => {
+                // The capacity is a power of two, so its top log2(capacity)
+                // bits index the table.
+                let shift = u64::BITS - self.capacity.trailing_zeros();
+                mixed
+                    .wrapping_mul(0x9E37_79B9_7F4A_7C15)
+                    .checked_shr(shift)
+                    .unwrap_or(0) as usize
+            }
+        }
     }
 
     /// Inserts a key-value pair into the map.
@@ -40,84 +244,255 @@ impl CustomHashMap {
     /// value is returned. The key is not updated, though; this matters for
     /// types that can be `==` without being identical.
     pub fn insert(&mut self, key: u8, value: u8) -> Option<u8> {
-        let mut current_index = 0;
-        while current_index < self.capacity {
-            let current_hash = (self.hash(key) + current_index) % self.capacity;
-            let current_slot = self.entries[current_hash];
-            match current_slot {
-                Slot::Vacant => {
-                    self.entries[current_hash] = Slot::Occupied { key, value };
-                    self.size += 1;
-                    return None;
-                }
-                Slot::Deleted => {
-                    self.entries[current_hash] = Slot::Occupied { key, value };
-                    self.size += 1;
-                    return None;
-                }
-                Slot::Occupied {
-                    key: current_key,
-                    value: current_value,
-                } => {
-                    if current_key == key {
-                        self.entries[current_hash] = Slot::Occupied { key, value };
-                        return Some(current_value);
-                    } else {
-                        current_index += 1;
-                    }
-                }
-            }
+        trace_event!(trace, key, value, "insert");
+        let index = self.insert_slot(key);
+        if is_full(self.ctrl[index]) {
+            return Some(std::mem::replace(&mut self.slots[index].1, value));
         }
+        self.occupy(index, key, value);
         None
     }
 
+    /// Inserts a key-value pair like [`insert`](Self::insert), and also
+    /// reports whether the table was resized to make room, which invalidates
+    /// every slot index handed out before.
+    pub fn insert_tracked(&mut self, key: u8, value: u8) -> (Option<u8>, bool) {
+        let capacity = self.capacity;
+        let old = self.insert(key, value);
+        (old, self.capacity != capacity)
+    }
+
+    /// Inserts a key-value pair like [`insert`](Self::insert), and also
+    /// returns how many slots, counting the home bucket, lie on the path to
+    /// the slot that ended up holding `key`. A resize along the way is not
+    /// counted; the length is measured in the final table.
+    pub fn insert_measured(&mut self, key: u8, value: u8) -> (Option<u8>, usize) {
+        trace_event!(trace, key, value, "insert");
+        let index = self.insert_slot(key);
+        let probes = self.probe_distance_from(key, index) + 1;
+        if is_full(self.ctrl[index]) {
+            return (
+                Some(std::mem::replace(&mut self.slots[index].1, value)),
+                probes,
+            );
+        }
+        self.occupy(index, key, value);
+        (None, probes)
+    }
+
+    /// Inserts a key-value pair like [`insert`](Self::insert), but fails
+    /// instead of growing when resizing is disabled with
+    /// [`allow_resize`](CustomHashMapBuilder::allow_resize) and a new key would
+    /// exceed the maximum load factor or the
+    /// [`max_probes`](CustomHashMapBuilder::max_probes) limit. The map is
+    /// unchanged on error.
+    pub fn checked_insert(&mut self, key: u8, value: u8) -> Result<Option<u8>, CapacityError> {
+        if !self.allow_resize {
+            let index = self.probe(key, true).ok_or(CapacityError)?;
+            if !self.can_use_slot(key, index) {
+                return Err(CapacityError);
+            }
+        }
+        Ok(self.insert(key, value))
+    }
+
+    /// Inserts `key` with `value` unless it is already present, and returns the
+    /// index of the slot holding `key` either way. An existing value is left
+    /// untouched.
+    ///
+    /// The index is only meaningful until the next structural modification of
+    /// the map, such as a remove, rehash or resize.
+    pub fn entry_index(&mut self, key: u8, value: u8) -> usize {
+        let index = self.insert_slot(key);
+        if !is_full(self.ctrl[index]) {
+            self.occupy(index, key, value);
+        }
+        index
+    }
+
+    /// Returns the slot holding `key`, or the slot an insert of `key` would
+    /// fill in the current table: the first vacant slot or tombstone on its
+    /// probe chain. Returns `None` if the chain has no free slot.
+    ///
+    /// This does not account for growth, so an insert that has to resize the
+    /// table first lands elsewhere.
+    pub fn would_place_at(&self, key: u8) -> Option<usize> {
+        self.probe(key, true)
+    }
+
+    /// Returns mutable references to the values in the slots at `indices`,
+    /// in the same order, for indices cached from
+    /// [`entry_index`](Self::entry_index).
+    ///
+    /// Returns `None` if any index is repeated, out of range or not an
+    /// occupied slot.
+    pub fn get_mut_by_indices(&mut self, indices: &[usize]) -> Option<Vec<&mut u8>> {
+        let ctrl = &self.ctrl;
+        let mut values: Vec<Option<&mut u8>> = self
+            .slots
+            .iter_mut()
+            .zip(ctrl)
+            .map(|((_, value), &ctrl)| is_full(ctrl).then_some(value))
+            .collect();
+        indices
+            .iter()
+            .map(|&index| values.get_mut(index)?.take())
+            .collect()
+    }
+
+    /// Sets the value of `key` to `new` if it currently equals `expected`,
+    /// returning `Ok(expected)`. Otherwise the map is unchanged and the
+    /// current value is returned as `Err(Some(current))`, or `Err(None)` if
+    /// the key is absent.
+    pub fn compare_and_swap(&mut self, key: u8, expected: u8, new: u8) -> Result<u8, Option<u8>> {
+        let index = self.find(key).ok_or(None)?;
+        let value = &mut self.slots[index].1;
+        if *value != expected {
+            return Err(Some(*value));
+        }
+        *value = new;
+        Ok(expected)
+    }
+
+    /// Inserts `default` if `key` is absent, or applies `f` to the existing
+    /// value, walking the key's probe chain only once.
+    pub fn insert_or_modify(&mut self, key: u8, default: u8, f: impl FnOnce(&mut u8)) {
+        let index = self.insert_slot(key);
+        if is_full(self.ctrl[index]) {
+            f(&mut self.slots[index].1);
+        } else {
+            self.occupy(index, key, default);
+        }
+    }
+
+    /// Applies `modify` to the value of `key` if it is present, or inserts
+    /// `default` otherwise, in one probe. Equivalent to
+    /// [`insert_or_modify`](Self::insert_or_modify), with the arguments in
+    /// `entry(key).and_modify(modify).or_insert(default)` order.
+    pub fn modify_or_insert(&mut self, key: u8, modify: impl FnOnce(&mut u8), default: u8) {
+        self.insert_or_modify(key, default, modify)
+    }
+
+    /// Returns a mutable reference to the value of `key`, inserting the
+    /// default value 0 first if it is absent.
+    pub fn get_mut_or_insert_default(&mut self, key: u8) -> &mut u8 {
+        let index = self.insert_slot(key);
+        if !is_full(self.ctrl[index]) {
+            self.occupy(index, key, u8::default());
+        }
+        &mut self.slots[index].1
+    }
+
+    /// Returns a mutable reference to the value of `key`, inserting the
+    /// result of `f` first if it is absent, but never grows the table: if a
+    /// new entry would need a resize, `f` is not called and
+    /// [`CapacityError`] is returned with the map unchanged.
+    pub fn try_get_or_insert_with(
+        &mut self,
+        key: u8,
+        f: impl FnOnce() -> u8,
+    ) -> Result<&mut u8, CapacityError> {
+        let index = self.probe(key, true).ok_or(CapacityError)?;
+        if !self.can_use_slot(key, index) {
+            return Err(CapacityError);
+        }
+        if !is_full(self.ctrl[index]) {
+            self.occupy(index, key, f());
+        }
+        Ok(&mut self.slots[index].1)
+    }
+
     /// Returns a reference to the value corresponding to the key.
     pub fn get(&self, key: u8) -> Option<&u8> {
-        let mut current_index = 0;
-        while current_index < self.capacity {
-            let current_hash = (self.hash(key) + current_index) % self.capacity;
-            let current_slot = &self.entries[current_hash];
-            match current_slot {
-                Slot::Vacant => return None,
-                Slot::Deleted => current_index += 1,
-                Slot::Occupied {
-                    key: current_key,
-                    value: current_value,
-                } => {
-                    if *current_key == key {
-                        return Some(current_value);
-                    }
-                    current_index += 1;
-                }
-            }
+        self.find(key).map(|index| &self.slots[index].1)
+    }
+
+    /// Returns a reference to the value corresponding to the key, or
+    /// `default` if the key is absent.
+    pub fn get_or<'a>(&'a self, key: u8, default: &'a u8) -> &'a u8 {
+        self.get(key).unwrap_or(default)
+    }
+
+    /// Walks the probe chain starting at bucket `hash % capacity` and returns
+    /// the value of the first live entry whose key satisfies `eq`.
+    ///
+    /// With the default seed and [`Hashing::Modulo`], a key's hash is the key
+    /// itself, so callers can compute it once and reuse it across lookups.
+    pub fn get_by_hash(&self, hash: usize, eq: impl FnMut(u8) -> bool) -> Option<&u8> {
+        self.find_by_hash(hash, eq)
+            .map(|index| &self.slots[index].1)
+    }
+
+    /// Resolves the slot index of each of `keys`, or `None` for absent keys,
+    /// for reading them back later through [`get_by_index`](Self::get_by_index).
+    ///
+    /// The indices stay valid while [`version`](Self::version) is unchanged.
+    pub fn precompute_lookups(&self, keys: &[u8]) -> Vec<Option<usize>> {
+        keys.iter().map(|&key| self.find(key)).collect()
+    }
+
+    /// Returns the entry in the slot at `index`, or `None` if the slot is
+    /// not occupied or out of range.
+    pub fn get_by_index(&self, index: usize) -> Option<(u8, &u8)> {
+        if !is_full(*self.ctrl.get(index)?) {
+            return None;
         }
-        None
+        let (key, value) = &self.slots[index];
+        Some((*key, value))
+    }
+
+    /// Returns a counter bumped by every change to which slots hold which
+    /// keys: inserting a new key, removing one, clearing or rehashing.
+    /// Updating an existing value leaves it unchanged.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    /// Gets a raw entry from a precomputed `hash`, for lookups that match
+    /// keys with a custom predicate. See [`get_by_hash`](Self::get_by_hash)
+    /// for how the hash is used.
+    pub fn raw_entry_mut(&mut self, hash: usize) -> RawEntryBuilderMut<'_> {
+        RawEntryBuilderMut { map: self, hash }
     }
 
     /// Removes a key from the map, returning the value at the key if the key
     /// was previously in the map.
     pub fn remove(&mut self, key: u8) -> Option<u8> {
-        let mut current_index = 0;
-        while current_index < self.capacity {
-            let current_hash = (self.hash(key) + current_index) % self.capacity;
-            let current_slot = self.entries[current_hash];
-            match current_slot {
-                Slot::Vacant => return None,
-                Slot::Deleted => current_index += 1,
-                Slot::Occupied {
-                    key: current_key,
-                    value,
-                } => {
-                    if current_key == key {
-                        self.entries[current_hash] = Slot::Deleted;
-                        self.size -= 1;
-                        return Some(value);
-                    }
-                    current_index += 1;
-                }
+        let index = self.find(key)?;
+        Some(self.remove_at(index).1)
+    }
+
+    /// Removes a key from the map only if `pred` returns `true` for its
+    /// value, returning the removed value.
+    ///
+    /// Returns [`None`] and leaves the map unchanged if the key is absent or
+    /// the predicate rejects its value.
+    pub fn remove_if(&mut self, key: u8, pred: impl FnOnce(&u8) -> bool) -> Option<u8> {
+        let index = self.find(key)?;
+        if !pred(&self.slots[index].1) {
+            return None;
+        }
+        Some(self.remove_at(index).1)
+    }
+
+    /// Removes every key in `keys` that is present and returns how many were
+    /// removed. Absent and repeated keys are skipped.
+    ///
+    /// If the batch leaves more tombstones than live entries, the table is
+    /// rehashed once at the end rather than carrying them.
+    pub fn remove_all(&mut self, keys: &[u8]) -> usize {
+        let mut removed = 0;
+        for &key in keys {
+            if let Some(index) = self.find(key) {
+                self.erase(index);
+                removed += 1;
             }
         }
-        None
+        self.shrink_if_sparse();
+        if removed > 0 && self.deleted > self.size {
+            self.rehash(self.capacity);
+        }
+        removed
     }
 
     /// Returns the number of elements in the map.
@@ -129,350 +504,3470 @@ impl CustomHashMap {
     pub fn is_empty(&self) -> bool {
         self.size == 0
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    /// Returns the number of slots in the table.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
 
-    #[test]
-    fn when_new_should_be_empty() {
-        let map = CustomHashMap::default();
-        assert_eq!(map.len(), 0);
-        assert!(map.is_empty());
+    /// Returns how many times the table has been reallocated to a different
+    /// capacity, by growing or shrinking, since the map was created.
+    pub fn resize_count(&self) -> usize {
+        self.resizes
     }
 
-    #[test]
-    fn when_insert_new_key_should_return_none() {
-        let mut map = CustomHashMap::default();
-        assert_eq!(map.insert(1, 10), None);
-        assert_eq!(map.len(), 1);
+    /// Returns the capacity the map was created with, before
+    /// [`Hashing::Fibonacci`] rounded it up to a power of two. Unlike
+    /// [`capacity`](Self::capacity) it does not follow later resizes.
+    pub fn requested_capacity(&self) -> usize {
+        self.requested_capacity
     }
 
-    #[test]
-    fn when_get_existing_key_should_return_value() {
-        let mut map = CustomHashMap::default();
-        map.insert(1, 10);
-        assert_eq!(map.get(1), Some(&10));
+    /// Empties the map, returning its entries in ascending key order.
+    ///
+    /// The map is cleared before the iterator is returned, so it is empty
+    /// afterwards even if the iterator is dropped early.
+    pub fn drain_sorted(&mut self) -> impl Iterator<Item = (u8, u8)> {
+        let mut entries: Vec<(u8, u8)> = self.live_entries().collect();
+        entries.sort_unstable_by_key(|&(key, _)| key);
+        self.reset();
+        entries.into_iter()
     }
 
-    #[test]
-    fn when_get_nonexistent_key_should_return_none() {
-        let map = CustomHashMap::default();
-        assert_eq!(map.get(1), None);
+    /// Removes the entries whose key falls in `range`, returning them in
+    /// physical slot order. Entries outside the range stay reachable.
+    ///
+    /// The entries are removed before the iterator is returned, so they are
+    /// gone even if the iterator is dropped early.
+    pub fn drain_range(&mut self, range: RangeInclusive<u8>) -> impl Iterator<Item = (u8, u8)> {
+        let mut drained = Vec::new();
+        for index in 0..self.capacity {
+            if is_full(self.ctrl[index]) && range.contains(&self.slots[index].0) {
+                drained.push(self.erase(index));
+            }
+        }
+        self.shrink_if_sparse();
+        drained.into_iter()
     }
 
-    #[test]
-    fn when_insert_existing_key_should_update_and_return_old() {
-        let mut map = CustomHashMap::default();
-        map.insert(1, 10);
-        assert_eq!(map.insert(1, 20), Some(10));
-        assert_eq!(map.get(1), Some(&20));
+    /// Clears the map and fills it from `iter`, keeping the existing table.
+    /// No reallocation happens when the new entries fit within the current
+    /// capacity and maximum load factor.
+    pub fn collect_into(&mut self, iter: impl IntoIterator<Item = (u8, u8)>) {
+        self.reset();
+        self.extend(iter);
     }
 
-    #[test]
-    fn when_hash_collision_should_probe_to_next_slot() {
-        let mut map = CustomHashMap::default();
-        map.insert(1, 10); // hash: 1
-        map.insert(9, 90); // hash: 1, should probe to 2
-        assert_eq!(map.get(1), Some(&10));
-        assert_eq!(map.get(9), Some(&90));
+    /// Returns references to the values of `a` and `b`.
+    ///
+    /// Both keys go through the same lookup as [`get`](Self::get), which
+    /// filters a chain by control byte a group of slots at a time, so walking
+    /// a chain the two keys share twice costs little.
+    pub fn get_pair(&self, a: u8, b: u8) -> (Option<&u8>, Option<&u8>) {
+        let value = |key| self.find(key).map(|index| &self.slots[index].1);
+        (value(a), value(b))
     }
 
-    #[test]
-    fn when_remove_existing_should_return_value() {
-        let mut map = CustomHashMap::default();
-        map.insert(1, 10);
-        assert_eq!(map.remove(1), Some(10));
-        assert_eq!(map.len(), 0);
+    /// Removes a key from the map, returning the stored key and value if the
+    /// key was previously in the map.
+    pub fn remove_entry(&mut self, key: u8) -> Option<(u8, u8)> {
+        let index = self.find(key)?;
+        Some(self.remove_at(index))
+    }
+
+    /// Removes a key from the map and hands back ownership of the key-value
+    /// pair. Equivalent to [`remove_entry`](Self::remove_entry).
+    pub fn take(&mut self, key: u8) -> Option<(u8, u8)> {
+        self.remove_entry(key)
+    }
+
+    /// Returns the maximum and mean probe distance over all live entries.
+    pub fn probe_stats(&self) -> ProbeStats {
+        let mut max = 0;
+        let mut total = 0;
+        for (index, &ctrl) in self.ctrl.iter().enumerate() {
+            if is_full(ctrl) {
+                let distance = self.probe_distance(index);
+                max = max.max(distance);
+                total += distance;
+            }
+        }
+        let mean = if self.size == 0 {
+            0.0
+        } else {
+            total as f64 / self.size as f64
+        };
+        ProbeStats { max, mean }
+    }
+
+    /// Returns how many live entries sit at each probe distance: index `i`
+    /// counts the entries exactly `i` slots past their home bucket. The
+    /// vector is one longer than the maximum distance, and empty for an empty
+    /// map.
+    pub fn probe_histogram(&self) -> Vec<usize> {
+        let mut histogram = Vec::new();
+        for (index, &ctrl) in self.ctrl.iter().enumerate() {
+            if is_full(ctrl) {
+                let distance = self.probe_distance(index);
+                if distance >= histogram.len() {
+                    histogram.resize(distance + 1, 0);
+                }
+                histogram[distance] += 1;
+            }
+        }
+        histogram
+    }
+
+    /// Iterates over the live entries that probing pushed out of their home
+    /// bucket, in physical slot order, as `(key, home_bucket, actual_slot)`.
+    pub fn displaced_entries(&self) -> impl Iterator<Item = (u8, usize, usize)> + '_ {
+        self.ctrl
+            .iter()
+            .zip(&self.slots)
+            .enumerate()
+            .filter(|(_, (&ctrl, _))| is_full(ctrl))
+            .map(|(index, (_, &(key, _)))| (key, self.hash(key), index))
+            .filter(|&(_, home, index)| home != index)
+    }
+
+    /// Returns `(start_index, length)` for each maximal run of consecutive
+    /// slots that are not vacant, counting tombstones as filled since probes
+    /// step over them too. A run that wraps past the last slot is reported
+    /// once, starting at its first slot before the wrap.
+    pub fn cluster_runs(&self) -> Vec<(usize, usize)> {
+        let mut runs = Vec::new();
+        let mut start = None;
+        for (index, &ctrl) in self.ctrl.iter().enumerate() {
+            match (ctrl == EMPTY, start) {
+                (false, None) => start = Some(index),
+                (true, Some(run_start)) => {
+                    runs.push((run_start, index - run_start));
+                    start = None;
+                }
+                _ => {}
+            }
+        }
+        if let Some(run_start) = start {
+            let mut length = self.capacity - run_start;
+            if let Some(&(0, wrapped)) = runs.first() {
+                runs.remove(0);
+                length += wrapped;
+            }
+            runs.push((run_start, length));
+        }
+        runs
+    }
+
+    /// Moves every entry whose key satisfies `pred` into a new map and
+    /// returns it.
+    ///
+    /// The returned map shares this map's configuration. This map is rehashed
+    /// afterwards, so neither map is left with tombstones.
+    pub fn split_off(&mut self, pred: impl Fn(u8) -> bool) -> CustomHashMap {
+        let mut split = self.empty_like(self.capacity);
+        for index in 0..self.capacity {
+            if is_full(self.ctrl[index]) && pred(self.slots[index].0) {
+                let (key, value) = self.erase(index);
+                split.insert(key, value);
+            }
+        }
+        self.rehash(self.capacity);
+        split
+    }
+
+    /// Copies the entries into two new maps: those with keys below `pivot`,
+    /// and the rest. This map is left unchanged, unlike with
+    /// [`split_off`](Self::split_off), and both results share its
+    /// configuration.
+    pub fn partition_by_key(&self, pivot: u8) -> (CustomHashMap, CustomHashMap) {
+        let mut low = self.empty_like(self.capacity);
+        let mut high = self.empty_like(self.capacity);
+        for (key, value) in self.live_entries() {
+            if key < pivot {
+                low.insert(key, value);
+            } else {
+                high.insert(key, value);
+            }
+        }
+        (low, high)
+    }
+
+    /// Returns a new map with an entry for every key present in either map,
+    /// valued `f(self_value, other_value)`; keys for which `f` returns
+    /// [`None`] are left out. Neither map is modified, and the result shares
+    /// this map's configuration.
+    ///
+    /// The result is sized up front for the union of both key sets, so it may
+    /// be larger than this map even when resizing is disabled on it.
+    pub fn merge_with(
+        &self,
+        other: &CustomHashMap,
+        f: impl Fn(Option<u8>, Option<u8>) -> Option<u8>,
+    ) -> CustomHashMap {
+        let union = self.size
+            + other
+                .live_entries()
+                .filter(|&(key, _)| self.find(key).is_none())
+                .count();
+        let mut merged = self.empty_like(self.capacity);
+        merged.allow_resize = true;
+        merged.reserve(union);
+        for (key, value) in self.live_entries() {
+            if let Some(value) = f(Some(value), other.get(key).copied()) {
+                merged.insert(key, value);
+            }
+        }
+        for (key, value) in other.live_entries() {
+            if self.find(key).is_none() {
+                if let Some(value) = f(None, Some(value)) {
+                    merged.insert(key, value);
+                }
+            }
+        }
+        merged.allow_resize = self.allow_resize;
+        merged
+    }
+
+    /// Decomposes the map into its control bytes, entry slots and length,
+    /// without copying.
+    ///
+    /// Slot `i` is live when `ctrl[i]` has its top bit clear (it then holds
+    /// the low seven bits of the key), empty when it is `0xFF` and a tombstone
+    /// when it is `0x80`. The map's configuration is not part of the result.
+    pub fn into_raw_parts(self) -> (Vec<u8>, Vec<(u8, u8)>, usize) {
+        (self.ctrl, self.slots, self.size)
+    }
+
+    /// Rebuilds a map from parts produced by
+    /// [`into_raw_parts`](Self::into_raw_parts), without copying. The map
+    /// gets the default configuration.
+    ///
+    /// The parts must describe a table built with the default hashing and a
+    /// seed of 0: `ctrl` and `slots` have the same length, every entry sits
+    /// on its key's probe chain with a matching control byte, and `size` is
+    /// the number of live slots. This is checked, in O(capacity), and
+    /// [`RawPartsError`] names the first invariant that does not hold.
+    /// Prefer [`from_pairs_checked`](Self::from_pairs_checked) when the
+    /// layout does not need to be preserved.
+    pub fn from_raw_parts(
+        ctrl: Vec<u8>,
+        slots: Vec<(u8, u8)>,
+        size: usize,
+    ) -> Result<Self, RawPartsError> {
+        let mut map = Self::with_capacity(0);
+        map.capacity = ctrl.len();
+        map.requested_capacity = ctrl.len();
+        map.deleted = ctrl.iter().filter(|&&ctrl| ctrl == DELETED).count();
+        map.ctrl = ctrl;
+        map.slots = slots;
+        map.size = size;
+        map.check_invariants().map_err(RawPartsError)?;
+        Ok(map)
+    }
+
+    /// Returns `true` if any key maps to `value`.
+    ///
+    /// This scans the whole table and is O(capacity).
+    pub fn contains_value(&self, value: u8) -> bool {
+        self.live_entries()
+            .any(|(_, current_value)| current_value == value)
+    }
+
+    /// Returns every live key for which `pred` returns `true`, in physical
+    /// slot order.
+    pub fn keys_matching(&self, pred: impl Fn(u8) -> bool) -> Vec<u8> {
+        self.live_entries()
+            .map(|(key, _)| key)
+            .filter(|&key| pred(key))
+            .collect()
+    }
+
+    /// Copies every live entry into a vector, in physical slot order.
+    ///
+    /// Iterators borrowing the map, like [`entries_mut`](Self::entries_mut),
+    /// must be dropped before the map is modified, and any insert or removal
+    /// may move entries to other slots. Iterate over a snapshot instead to
+    /// modify the map while walking its entries.
+    pub fn snapshot(&self) -> Vec<(u8, u8)> {
+        self.live_entries().collect()
+    }
+
+    /// Copies every live entry into a new std [`HashMap`].
+    pub fn to_std_hashmap(&self) -> HashMap<u8, u8> {
+        self.live_entries().collect()
+    }
+
+    /// Rehashes the entries into a tombstone-free table sized for a load of at
+    /// most 7/8 and returns it as a read-only map. A registered resize callback
+    /// is dropped. A map that may not resize keeps its capacity.
+    pub fn freeze(mut self) -> FrozenHashMap {
+        self.on_resize = None;
+        let capacity = self.size + self.size / 7 + 1;
+        if self.resize(self.hashing.round_capacity(capacity)).is_err() {
+            self.rehash(self.capacity);
+        }
+        FrozenHashMap { map: self }
+    }
+
+    /// Returns every value held by more than one key, paired with those keys
+    /// in ascending order. Values are reported in ascending order too.
+    pub fn duplicate_value_keys(&self) -> Vec<(u8, Vec<u8>)> {
+        let mut keys_by_value: Vec<Vec<u8>> = vec![Vec::new(); u8::MAX as usize + 1];
+        for (key, value) in self.live_entries() {
+            keys_by_value[value as usize].push(key);
+        }
+        keys_by_value
+            .into_iter()
+            .enumerate()
+            .filter(|(_, keys)| keys.len() > 1)
+            .map(|(value, mut keys)| {
+                keys.sort_unstable();
+                (value as u8, keys)
+            })
+            .collect()
+    }
+
+    /// Registers `f` to be called with `(old_capacity, new_capacity)` each
+    /// time the table is reallocated to a different capacity. Replaces any
+    /// previously registered callback.
+    ///
+    /// The callback must be `Send` and `Sync` so the map stays `Send` and
+    /// `Sync`.
+    pub fn on_resize(&mut self, f: impl FnMut(usize, usize) + Send + Sync + 'static) {
+        self.on_resize = Some(Box::new(f));
+    }
+
+    /// Hands resize decisions to `policy`, replacing the one the map was
+    /// built with. See [`CustomHashMapBuilder::capacity_policy`].
+    pub fn set_capacity_policy(&mut self, policy: impl CapacityPolicy + 'static) {
+        self.capacity_policy = Arc::new(policy);
+    }
+
+    /// Gets the entry for `key` for in-place manipulation.
+    ///
+    /// Looking up the entry never resizes the table. If a vacant entry's
+    /// insert needs more room, the table grows then and the slot is resolved
+    /// against the grown table.
+    pub fn entry(&mut self, key: u8) -> Entry<'_> {
+        match self.find(key) {
+            Some(index) => Entry::Occupied(OccupiedEntry { map: self, index }),
+            None => Entry::Vacant(VacantEntry { map: self, key }),
+        }
+    }
+
+    /// Returns the live load and the probe load (live entries plus
+    /// tombstones) of the table.
+    pub fn utilization(&self) -> Utilization {
+        if self.capacity == 0 {
+            return Utilization {
+                live_load: 0.0,
+                probe_load: 0.0,
+            };
+        }
+        let capacity = self.capacity as f64;
+        Utilization {
+            live_load: self.size as f64 / capacity,
+            probe_load: (self.size + self.deleted) as f64 / capacity,
+        }
+    }
+
+    /// Grows the table, in at most one resize, so that `additional` more
+    /// entries fit without exceeding the maximum load factor.
+    ///
+    /// If the live entries fit but tombstones would push the probe load past
+    /// the maximum, the table is rehashed at its current capacity instead of
+    /// growing.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the table would have to grow but resizing is disabled with
+    /// [`allow_resize`](CustomHashMapBuilder::allow_resize). Use
+    /// [`try_reserve`](Self::try_reserve) to handle that case.
+    pub fn reserve(&mut self, additional: usize) {
+        self.try_reserve(additional)
+            .expect("reserve needs to grow the map but resizing is disabled");
+    }
+
+    /// Like [`reserve`](Self::reserve), but returns [`CapacityError`],
+    /// leaving the map unchanged, if the table would have to grow while
+    /// resizing is disabled.
+    pub fn try_reserve(&mut self, additional: usize) -> Result<(), CapacityError> {
+        let needed = self.size + additional;
+        if needed <= self.max_load() {
+            if needed + self.deleted > self.max_load() {
+                self.rehash(self.capacity);
+            }
+            return Ok(());
+        }
+        let mut capacity = (needed as f64 / self.max_load_factor).ceil() as usize;
+        while ((capacity as f64) * self.max_load_factor) < needed as f64 {
+            capacity += 1;
+        }
+        self.resize(self.hashing.round_capacity(capacity))
+    }
+
+    /// Grows the table, in at most one resize, to the smallest power-of-two
+    /// capacity that holds `total` entries without exceeding the maximum load
+    /// factor.
+    ///
+    /// Unlike [`reserve`](Self::reserve), `total` counts the entries already
+    /// in the map. The table is never shrunk.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the table would have to grow but resizing is disabled with
+    /// [`allow_resize`](CustomHashMapBuilder::allow_resize).
+    pub fn reserve_for_total(&mut self, total: usize) {
+        if total <= self.max_load() {
+            return;
+        }
+        let mut capacity = self.capacity.max(1).next_power_of_two();
+        while ((capacity as f64) * self.max_load_factor) < total as f64 {
+            capacity *= 2;
+        }
+        self.resize(capacity)
+            .expect("reserve_for_total needs to grow the map but resizing is disabled");
+    }
+
+    /// Shrinks the table to the smallest capacity that holds the current
+    /// entries within the maximum load factor, but never below the
+    /// [minimum capacity](Self::set_min_capacity). Also drops all tombstones,
+    /// which is all it does to a map that may not resize.
+    pub fn shrink_to_fit(&mut self) {
+        let mut capacity = (self.size as f64 / self.max_load_factor).ceil() as usize;
+        while ((capacity as f64) * self.max_load_factor) < self.size as f64 {
+            capacity += 1;
+        }
+        let capacity = self.hashing.round_capacity(capacity.max(self.min_capacity));
+        if (capacity < self.capacity || self.deleted > 0)
+            && self.resize(capacity.min(self.capacity)).is_err()
+        {
+            self.rehash(self.capacity);
+        }
+    }
+
+    /// Moves the entries into a fresh table of `new_capacity` slots, rounded
+    /// up to a power of two under [`Hashing::Fibonacci`], growing or
+    /// shrinking the table and dropping all tombstones in one pass.
+    ///
+    /// Returns [`CapacityError`], leaving the map unchanged, if the new table
+    /// cannot hold the current entries within the maximum load factor, or if
+    /// the capacity would change while resizing is disabled.
+    pub fn rebuild_with_capacity(&mut self, new_capacity: usize) -> Result<(), CapacityError> {
+        let new_capacity = self.hashing.round_capacity(new_capacity);
+        if (new_capacity as f64 * self.max_load_factor) < self.size as f64 {
+            return Err(CapacityError);
+        }
+        self.resize(new_capacity)
+    }
+
+    /// Sets the capacity [`shrink_to_fit`](Self::shrink_to_fit) and automatic
+    /// shrinking stop at, so a nearly empty map does not shrink to a table
+    /// that has to grow again right away. Defaults to 8.
+    pub fn set_min_capacity(&mut self, min_capacity: usize) {
+        self.min_capacity = min_capacity;
+    }
+
+    /// Removes and returns an arbitrary entry: the one in the lowest occupied
+    /// slot. Calling it until it returns [`None`] drains the map.
+    pub fn pop(&mut self) -> Option<(u8, u8)> {
+        let index = self.ctrl.iter().position(|&ctrl| is_full(ctrl))?;
+        Some(self.remove_at(index))
+    }
+
+    /// Looks up every key in `keys` and returns the copied values in the same
+    /// order, with [`None`] for absent keys.
+    pub fn get_all(&self, keys: &[u8]) -> Vec<Option<u8>> {
+        keys.iter().map(|&key| self.get(key).copied()).collect()
+    }
+
+    /// Inserts `pairs`, which must be sorted by home bucket at the map's
+    /// current capacity, so consecutive inserts walk the table front to back
+    /// and touch neighbouring slots. The order is only checked in debug
+    /// builds; unsorted input is still inserted correctly, just without the
+    /// locality.
+    ///
+    /// Room for every pair is reserved up front if the map may resize. Call
+    /// [`reserve`](Self::reserve) before sorting if that could grow the table,
+    /// since growing changes every key's home bucket.
+    pub fn insert_many_sorted(&mut self, pairs: &[(u8, u8)]) {
+        debug_assert!(
+            self.capacity == 0
+                || pairs
+                    .windows(2)
+                    .all(|pair| self.hash(pair[0].0) <= self.hash(pair[1].0)),
+            "pairs are not sorted by home bucket"
+        );
+        self.try_reserve(pairs.len()).ok();
+        for &(key, value) in pairs {
+            self.insert(key, value);
+        }
+    }
+
+    /// Counts occurrences: for each key, inserts 1 if it is absent or adds 1
+    /// (wrapping) to its value.
+    ///
+    /// Room for every new distinct key is reserved up front if the map may
+    /// resize, and a run of
+    /// repeated keys reuses the slot found for its first occurrence.
+    pub fn bump_all(&mut self, keys: &[u8]) {
+        let mut seen = [false; 256];
+        let mut new_keys = 0;
+        for &key in keys {
+            if !seen[key as usize] {
+                seen[key as usize] = true;
+                new_keys += usize::from(self.find(key).is_none());
+            }
+        }
+        self.try_reserve(new_keys).ok();
+
+        let mut last: Option<(u8, usize)> = None;
+        for &key in keys {
+            let index = match last {
+                Some((last_key, index)) if last_key == key => index,
+                _ => self.insert_slot(key),
+            };
+            if is_full(self.ctrl[index]) {
+                let value = &mut self.slots[index].1;
+                *value = value.wrapping_add(1);
+            } else {
+                self.occupy(index, key, 1);
+            }
+            last = Some((key, index));
+        }
+    }
+
+    /// Counts occurrences like [`bump_all`](Self::bump_all), wrapping on
+    /// overflow, and returns how many of the keys were new to the map.
+    pub fn tally(&mut self, keys: impl IntoIterator<Item = u8>) -> usize {
+        let mut created = 0;
+        for key in keys {
+            let index = self.insert_slot(key);
+            if is_full(self.ctrl[index]) {
+                let value = &mut self.slots[index].1;
+                *value = value.wrapping_add(1);
+            } else {
+                self.occupy(index, key, 1);
+                created += 1;
+            }
+        }
+        created
+    }
+
+    /// Counts one occurrence of `key`: inserts 1 if it is absent or adds 1 to
+    /// its value, stopping at 255 instead of wrapping. Returns the new count.
+    pub fn saturating_bump(&mut self, key: u8) -> u8 {
+        match self.entry(key) {
+            Entry::Occupied(mut entry) => {
+                let value = entry.get_mut();
+                *value = value.saturating_add(1);
+                *value
+            }
+            Entry::Vacant(entry) => *entry.insert(1),
+        }
+    }
+
+    /// Iterates over every slot in physical order, including vacant slots and
+    /// tombstones, paired with its index.
+    pub fn iter_slots(&self) -> impl Iterator<Item = (usize, SlotView)> + '_ {
+        self.ctrl
+            .iter()
+            .zip(&self.slots)
+            .map(|(&ctrl, &(key, value))| match ctrl {
+                EMPTY => SlotView::Vacant,
+                DELETED => SlotView::Deleted,
+                _ => SlotView::Occupied { key, value },
+            })
+            .enumerate()
+    }
+
+    /// Renders every slot in physical order as a stable, comma-separated
+    /// string for layout snapshot tests: `V` for a vacant slot, `D` for a
+    /// tombstone and `O(key:value)` for an occupied one.
+    pub fn dump_layout(&self) -> String {
+        self.iter_slots()
+            .map(|(_, slot)| match slot {
+                SlotView::Vacant => "V".to_string(),
+                SlotView::Deleted => "D".to_string(),
+                SlotView::Occupied { key, value } => format!("O({key}:{value})"),
+            })
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+
+    /// Calls `f` with every live entry, in physical slot order.
+    pub fn for_each_entry(&self, mut f: impl FnMut(u8, &u8)) {
+        for (&ctrl, (key, value)) in self.ctrl.iter().zip(&self.slots) {
+            if is_full(ctrl) {
+                f(*key, value);
+            }
+        }
+    }
+
+    /// Iterates over the live entries in physical slot order, yielding each
+    /// key with a mutable reference to its value.
+    pub fn entries_mut(&mut self) -> impl Iterator<Item = (u8, &mut u8)> {
+        self.ctrl
+            .iter()
+            .zip(&mut self.slots)
+            .filter(|(&ctrl, _)| is_full(ctrl))
+            .map(|(_, (key, value))| (*key, value))
+    }
+
+    /// Builds a map from `pairs`, keeping the last value for duplicate keys.
+    ///
+    /// This is the safe way to reconstruct a map from persisted entries.
+    pub fn from_pairs_checked(pairs: Vec<(u8, u8)>) -> Self {
+        let mut map = Self::default();
+        map.extend(pairs);
+        map
+    }
+
+    /// Builds a map from fallible pairs, stopping at the first `Err`.
+    ///
+    /// The partially built map is dropped and the error returned as is.
+    pub fn try_from_iter<E>(
+        iter: impl IntoIterator<Item = Result<(u8, u8), E>>,
+    ) -> Result<Self, E> {
+        let mut map = Self::default();
+        for pair in iter {
+            let (key, value) = pair?;
+            map.insert(key, value);
+        }
+        Ok(map)
+    }
+
+    /// Checks the table's internal invariants, panicking with a description
+    /// of the first one that is broken.
+    ///
+    /// Every live slot must carry its key's control byte and be the slot a
+    /// lookup for that key finds, and the length and tombstone counts must
+    /// match the control bytes. This is O(capacity).
+    pub fn validate(&self) {
+        if let Err(message) = self.check_invariants() {
+            panic!("{message}");
+        }
+    }
+
+    /// Describes the first broken invariant for [`validate`](Self::validate)
+    /// and [`from_raw_parts`](Self::from_raw_parts).
+    fn check_invariants(&self) -> Result<(), String> {
+        let capacity = self.capacity;
+        if self.ctrl.len() != capacity {
+            let ctrl = self.ctrl.len();
+            return Err(format!("control bytes != capacity ({ctrl} != {capacity})"));
+        }
+        if self.slots.len() != capacity {
+            let slots = self.slots.len();
+            return Err(format!("entry slots != capacity ({slots} != {capacity})"));
+        }
+        let live = self.ctrl.iter().filter(|&&ctrl| is_full(ctrl)).count();
+        if live != self.size {
+            return Err(format!("size != live slot count ({} != {live})", self.size));
+        }
+        let tombstones = self.ctrl.iter().filter(|&&ctrl| ctrl == DELETED).count();
+        if tombstones != self.deleted {
+            let deleted = self.deleted;
+            return Err(format!(
+                "deleted != tombstone count ({deleted} != {tombstones})"
+            ));
+        }
+        for (index, &ctrl) in self.ctrl.iter().enumerate() {
+            if !is_full(ctrl) {
+                continue;
+            }
+            let key = self.slots[index].0;
+            if ctrl != h2(key) {
+                return Err(format!("slot {index} control byte mismatch"));
+            }
+            if self.find(key) != Some(index) {
+                return Err(format!("key {key} unreachable"));
+            }
+        }
+        Ok(())
+    }
+
+    /// Counts how often each byte value occurs in `data`, returning a map from
+    /// byte value to count. Counts wrap past 255, as in
+    /// [`bump_all`](Self::bump_all).
+    ///
+    /// The table is sized up front for all 256 byte values, so it never grows
+    /// while counting.
+    pub fn byte_histogram(data: &[u8]) -> CustomHashMap {
+        let mut map = Self::with_capacity(0);
+        map.reserve(u8::MAX as usize + 1);
+        map.bump_all(data);
+        map
+    }
+
+    /// Retains only the entries for which `f` returns `true`, passing each
+    /// key and a mutable reference to its value.
+    ///
+    /// The table is rehashed at its current capacity afterwards if anything
+    /// was removed, so it is left with no tombstones.
+    pub fn retain_compact(&mut self, mut f: impl FnMut(&u8, &mut u8) -> bool) {
+        let mut removed = false;
+        for index in 0..self.capacity {
+            if !is_full(self.ctrl[index]) {
+                continue;
+            }
+            let (key, value) = &mut self.slots[index];
+            if !f(key, value) {
+                self.erase(index);
+                removed = true;
+            }
+        }
+        if removed {
+            self.rehash(self.capacity);
+        }
+    }
+
+    /// Hints the CPU to pull the home bucket of `key` into cache, so a batch
+    /// of lookups or updates can prefetch a few keys ahead of the one it is
+    /// working on. The map is not changed.
+    ///
+    /// This is a no-op on targets without a prefetch instruction.
+    pub fn prefetch(&self, key: u8) {
+        if self.capacity == 0 {
+            return;
+        }
+        let home = self.hash(key);
+        prefetch_read(&self.ctrl[home]);
+        prefetch_read(&self.slots[home]);
+    }
+
+    /// Iterates over the values in ascending order of their keys.
+    pub fn values_sorted_by_key(&self) -> impl Iterator<Item = &u8> {
+        let mut entries: Vec<(u8, &u8)> = self
+            .ctrl
+            .iter()
+            .zip(&self.slots)
+            .filter(|(&ctrl, _)| is_full(ctrl))
+            .map(|(_, (key, value))| (*key, value))
+            .collect();
+        entries.sort_unstable_by_key(|&(key, _)| key);
+        entries.into_iter().map(|(_, value)| value)
+    }
+
+    /// Returns the bytes used by the map: the struct itself plus the heap
+    /// allocations of its control bytes and entry slots. A registered resize
+    /// callback is not counted.
+    pub fn memory_usage(&self) -> usize {
+        std::mem::size_of::<Self>()
+            + self.ctrl.capacity() * std::mem::size_of::<u8>()
+            + self.slots.capacity() * std::mem::size_of::<(u8, u8)>()
+    }
+
+    /// Returns the slot holding `key`, or the free slot a new `key` should be
+    /// written to. Grows the table first if one more live entry would exceed
+    /// the maximum load factor or land more than `max_probes` slots from home.
+    ///
+    /// Tombstones do not count towards growing, since a new key can reuse
+    /// them. If the key needs a vacant slot instead and live entries plus
+    /// tombstones would pass the maximum load, the table is rehashed at the
+    /// same capacity to clear the tombstones.
+    fn insert_slot(&mut self, key: u8) -> usize {
+        loop {
+            if let Some(index) = self.probe(key, true) {
+                if self.ctrl[index] == EMPTY
+                    && self.deleted > 0
+                    && self.size + self.deleted >= self.max_load()
+                {
+                    self.rehash(self.capacity);
+                    continue;
+                }
+                if self.can_use_slot(key, index) {
+                    #[cfg(feature = "tracing")]
+                    {
+                        let probes = self.probe_distance_from(key, index) + 1;
+                        if probes > LONG_PROBE_CHAIN {
+                            tracing::debug!(key, probes, "long probe chain");
+                        }
+                    }
+                    return index;
+                }
+            }
+            self.grow();
+        }
+    }
+
+    /// Whether `key` may be written to `index`, as found by `probe`: either
+    /// it already holds `key`, or it is free, one more entry stays within the
+    /// maximum load factor and the slot is within `max_probes` of home.
+    fn can_use_slot(&self, key: u8, index: usize) -> bool {
+        if is_full(self.ctrl[index]) {
+            return true;
+        }
+        let probes = self.probe_distance_from(key, index) + 1;
+        self.size < self.max_load() && probes <= self.max_probes
+    }
+
+    /// Writes a new entry into the free slot at `index`.
+    fn occupy(&mut self, index: usize, key: u8, value: u8) {
+        if self.ctrl[index] == DELETED {
+            self.deleted -= 1;
+        }
+        self.ctrl[index] = h2(key);
+        self.slots[index] = (key, value);
+        self.size += 1;
+        self.version += 1;
+        self.check_size();
+    }
+
+    /// Number of entries the table holds before it has to grow.
+    fn max_load(&self) -> usize {
+        (self.capacity as f64 * self.max_load_factor) as usize
+    }
+
+    fn grow(&mut self) {
+        assert!(
+            self.allow_resize,
+            "map is at capacity and resizing is disabled"
+        );
+        let grown = self
+            .capacity_policy
+            .should_grow(self.size, self.capacity)
+            .expect("map is at capacity and the capacity policy refused to grow");
+        self.resize(self.hashing.round_capacity(grown.max(self.capacity + 1)))
+            .expect("resizing is allowed");
+    }
+
+    /// Rehashes into a table of `new_capacity` slots, or fails with
+    /// [`CapacityError`] and leaves the map unchanged if that changes the
+    /// capacity while resizing is disabled. Every change of capacity goes
+    /// through here.
+    fn resize(&mut self, new_capacity: usize) -> Result<(), CapacityError> {
+        if new_capacity != self.capacity && !self.allow_resize {
+            return Err(CapacityError);
+        }
+        self.rehash(new_capacity);
+        Ok(())
+    }
+
+    /// Rehashes every live entry into a fresh table of `new_capacity` slots,
+    /// dropping all tombstones. Callers other than [`resize`](Self::resize)
+    /// keep the capacity as it is.
+    fn rehash(&mut self, new_capacity: usize) {
+        let old_capacity = self.capacity;
+        let entries: Vec<(u8, u8)> = self.live_entries().collect();
+        self.ctrl = vec![EMPTY; new_capacity];
+        self.slots = vec![(0, 0); new_capacity];
+        self.capacity = new_capacity;
+        self.size = 0;
+        self.deleted = 0;
+        self.version += 1;
+        for (key, value) in entries {
+            let index = self
+                .probe(key, true)
+                .expect("resized table has a free slot");
+            if is_full(self.ctrl[index]) {
+                self.slots[index].1 = value;
+            } else {
+                self.occupy(index, key, value);
+            }
+        }
+        if new_capacity == old_capacity {
+            trace_event!(debug, capacity = new_capacity, "compacted");
+        } else {
+            trace_event!(debug, old_capacity, new_capacity, "resized");
+            self.resizes += 1;
+            if let Some(hook) = &mut self.on_resize {
+                hook(old_capacity, new_capacity);
+            }
+        }
+    }
+
+    /// Removes the entry at `index` on behalf of a public removal, shrinking
+    /// the table afterwards if that policy is enabled.
+    fn remove_at(&mut self, index: usize) -> (u8, u8) {
+        trace_event!(trace, key = self.slots[index].0, "remove");
+        let entry = self.erase(index);
+        self.shrink_if_sparse();
+        entry
+    }
+
+    /// In this crate's own debug test builds, recounts the live slots and
+    /// tombstones and asserts `size` and `deleted` still match and that no key
+    /// is stored twice. Called from [`occupy`](Self::occupy) and
+    /// [`erase`](Self::erase), the only places a single entry comes or goes.
+    /// Compiled out everywhere else, since the count is O(capacity).
+    fn check_size(&self) {
+        if cfg!(all(test, debug_assertions)) {
+            let mut seen = [false; 256];
+            let mut live = 0;
+            for (&ctrl, &(key, _)) in self.ctrl.iter().zip(&self.slots) {
+                if is_full(ctrl) {
+                    assert!(!seen[key as usize], "key {key} is stored twice");
+                    seen[key as usize] = true;
+                    live += 1;
+                }
+            }
+            assert_eq!(live, self.size, "size != live slot count");
+            let tombstones = self.ctrl.iter().filter(|&&ctrl| ctrl == DELETED).count();
+            assert_eq!(tombstones, self.deleted, "deleted != tombstone count");
+        }
+    }
+
+    /// After a removal, shrinks the table to the capacity policy's target.
+    /// Never goes below `min_capacity` or to a table too small for the live
+    /// entries, and leaves a map that may not resize alone.
+    fn shrink_if_sparse(&mut self) {
+        let Some(target) = self.capacity_policy.should_shrink(self.size, self.capacity) else {
+            return;
+        };
+        let shrunk = self.hashing.round_capacity(target.max(self.min_capacity));
+        if shrunk < self.capacity && shrunk as f64 * self.max_load_factor >= self.size as f64 {
+            self.resize(shrunk).ok();
+        }
+    }
+
+    /// Frees an occupied slot and returns the entry it held. See
+    /// [`free_slot`] for when the slot becomes a tombstone.
+    fn erase(&mut self, index: usize) -> (u8, u8) {
+        self.size -= 1;
+        self.version += 1;
+        free_slot(&mut self.ctrl, index, &mut self.deleted);
+        self.check_size();
+        self.slots[index]
+    }
+
+    /// Copies of every live entry, in physical slot order.
+    fn live_entries(&self) -> impl Iterator<Item = (u8, u8)> + '_ {
+        self.ctrl
+            .iter()
+            .zip(&self.slots)
+            .filter(|(&ctrl, _)| is_full(ctrl))
+            .map(|(_, &entry)| entry)
+    }
+
+    /// Marks every slot `EMPTY`, dropping all entries and tombstones.
+    fn reset(&mut self) {
+        self.ctrl.fill(EMPTY);
+        self.size = 0;
+        self.deleted = 0;
+        self.version += 1;
+    }
+
+    /// How many slots past its home bucket the entry at `index` sits.
+    fn probe_distance(&self, index: usize) -> usize {
+        self.probe_distance_from(self.slots[index].0, index)
+    }
+
+    /// How many slots past the home bucket of `key` the slot `index` is.
+    fn probe_distance_from(&self, key: u8, index: usize) -> usize {
+        (index + self.capacity - self.hash(key)) % self.capacity
+    }
+
+    /// An empty map with the same configuration and `capacity` slots.
+    fn empty_like(&self, capacity: usize) -> CustomHashMap {
+        let mut map = CustomHashMap::builder()
+            .capacity(capacity)
+            .max_load_factor(self.max_load_factor)
+            .seed(self.seed)
+            .hashing(self.hashing)
+            .allow_resize(self.allow_resize)
+            .max_probes(self.max_probes)
+            .build();
+        map.min_capacity = self.min_capacity;
+        map.capacity_policy = Arc::clone(&self.capacity_policy);
+        map
+    }
+
+    /// Returns the slot index of the first live entry on the probe chain
+    /// starting at `hash % capacity` whose key satisfies `eq`.
+    fn find_by_hash(&self, hash: usize, mut eq: impl FnMut(u8) -> bool) -> Option<usize> {
+        if self.capacity == 0 {
+            return None;
+        }
+        for current_index in 0..self.capacity {
+            let current_hash = (hash + current_index) % self.capacity;
+            match self.ctrl[current_hash] {
+                EMPTY => break,
+                DELETED => continue,
+                _ => {}
+            }
+            if eq(self.slots[current_hash].0) {
+                return Some(current_hash);
+            }
+        }
+        None
+    }
+
+    /// Returns the slot index holding `key`.
+    fn find(&self, key: u8) -> Option<usize> {
+        self.probe(key, false)
+            .filter(|&index| is_full(self.ctrl[index]))
+    }
+
+    fn probe(&self, key: u8, reuse_deleted: bool) -> Option<usize> {
+        if self.capacity == 0 {
+            return None;
+        }
+        probe(
+            &self.ctrl,
+            &self.slots,
+            self.hash(key),
+            key,
+            h2(key),
+            reuse_deleted,
+        )
+    }
+}
+
+#[cfg(test)]
+impl CustomHashMap {
+    /// Asserts that the map holds exactly the entries of `reference`, for
+    /// differential tests against [`HashMap`]. The panic message lists every
+    /// key whose value differs as `key: ours != reference`.
+    pub fn assert_matches(&self, reference: &HashMap<u8, u8>) {
+        let diff: Vec<String> = (0..=u8::MAX)
+            .filter_map(|key| {
+                let ours = self.get(key);
+                let theirs = reference.get(&key);
+                (ours != theirs).then(|| format!("{key}: {ours:?} != {theirs:?}"))
+            })
+            .collect();
+        assert!(
+            diff.is_empty() && self.len() == reference.len(),
+            "map differs from reference (len {} != {}): {}",
+            self.len(),
+            reference.len(),
+            diff.join(", ")
+        );
+    }
+}
+
+impl Extend<(u8, u8)> for CustomHashMap {
+    /// Reserves room up front using the iterator's upper size hint when it
+    /// has one, capped at the 256 distinct `u8` keys, so an exact-size
+    /// iterator causes at most one resize. A map that may not resize skips
+    /// the reservation and inserts the items one by one, panicking like
+    /// [`CustomHashMap::insert`] once a new key does not fit.
+    fn extend<T: IntoIterator<Item = (u8, u8)>>(&mut self, iter: T) {
+        let iter = iter.into_iter();
+        let (lower, upper) = iter.size_hint();
+        let key_space = u8::MAX as usize + 1;
+        self.try_reserve(
+            upper
+                .unwrap_or(lower)
+                .min(key_space.saturating_sub(self.size)),
+        )
+        .ok();
+        for (key, value) in iter {
+            self.insert(key, value);
+        }
+    }
+}
+
+impl From<CustomHashMap> for HashMap<u8, u8> {
+    fn from(map: CustomHashMap) -> Self {
+        map.to_std_hashmap()
+    }
+}
+
+impl<const N: usize> From<DefaultCapacity<N>> for CustomHashMap {
+    fn from(_: DefaultCapacity<N>) -> Self {
+        Self::with_default_capacity::<N>()
+    }
+}
+
+impl From<HashMap<u8, u8>> for CustomHashMap {
+    /// Sizes the table for the input's length up front, so building it
+    /// resizes at most once.
+    fn from(std_map: HashMap<u8, u8>) -> Self {
+        let mut map = Self::default();
+        map.extend(std_map);
+        map
+    }
+}
+
+/// Prefetches the cache line holding `value` for reading.
+#[cfg(all(target_arch = "x86_64", target_feature = "sse"))]
+fn prefetch_read<T>(value: &T) {
+    use std::arch::x86_64::{_mm_prefetch, _MM_HINT_T0};
+    // SAFETY: SSE is enabled at compile time, and a prefetch never faults.
+    unsafe { _mm_prefetch::<_MM_HINT_T0>((value as *const T).cast()) }
+}
+
+#[cfg(not(all(target_arch = "x86_64", target_feature = "sse")))]
+fn prefetch_read<T>(_value: &T) {}
+
+/// Frees the occupied slot at `index`, counting tombstones in `deleted`.
+///
+/// The slot is only tombstoned if the next slot is occupied or a tombstone
+/// itself. When the next slot is `EMPTY`, every probe chain through this slot
+/// already ends there, so the slot is marked `EMPTY` instead, along with the
+/// run of tombstones right before it.
+fn free_slot(ctrl: &mut [u8], index: usize, deleted: &mut usize) {
+    let capacity = ctrl.len();
+    if ctrl[(index + 1) % capacity] != EMPTY {
+        ctrl[index] = DELETED;
+        *deleted += 1;
+        return;
+    }
+    ctrl[index] = EMPTY;
+    let mut previous = (index + capacity - 1) % capacity;
+    while ctrl[previous] == DELETED {
+        ctrl[previous] = EMPTY;
+        *deleted -= 1;
+        previous = (previous + capacity - 1) % capacity;
+    }
+}
+
+/// Scrambles `key` with `seed` before it is reduced to a bucket. The seed is
+/// folded in first and the result run through the splitmix64 finalizer, so
+/// keys that share a bucket under one seed land in unrelated buckets under
+/// another. A seed of 0 leaves the key untouched.
+fn mix(key: u8, seed: u64) -> u64 {
+    if seed == 0 {
+        return key as u64;
+    }
+    let mut x = (key as u64 ^ seed).wrapping_add(0x9E37_79B9_7F4A_7C15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    x ^ (x >> 31)
+}
+
+/// Walks the probe sequence of `key` from its `home` slot and returns the slot
+/// holding `key`, or else the slot a new `key` belongs in: the first `EMPTY`
+/// one, or with `reuse_deleted` the first `DELETED` one before it. `tag` is the
+/// control byte a slot holding `key` carries.
+///
+/// A tombstone is only reused once the rest of the chain has been ruled out,
+/// since `key` may still sit further along it.
+///
+/// Whole groups of control bytes are scanned at once while the window neither
+/// wraps around nor overruns the probe budget; the remaining slots are checked
+/// one by one.
+fn probe<K: Copy + PartialEq>(
+    ctrl: &[u8],
+    slots: &[(K, u8)],
+    home: usize,
+    key: K,
+    tag: u8,
+    reuse_deleted: bool,
+) -> Option<usize> {
+    let capacity = ctrl.len();
+    let mut first_deleted = None;
+    let mut current_index = 0;
+    while current_index < capacity {
+        let current_hash = (home + current_index) % capacity;
+        if current_hash + WIDTH <= capacity && current_index + WIDTH <= capacity {
+            let group = Group::load(ctrl[current_hash..current_hash + WIDTH].try_into().unwrap());
+            let stop = group.match_byte(EMPTY).lowest().unwrap_or(WIDTH);
+            for offset in group.match_byte(tag).below(stop) {
+                if slots[current_hash + offset].0 == key {
+                    return Some(current_hash + offset);
+                }
+            }
+            if reuse_deleted && first_deleted.is_none() {
+                first_deleted = group
+                    .match_empty_or_deleted()
+                    .below(stop)
+                    .lowest()
+                    .map(|offset| current_hash + offset);
+            }
+            if stop < WIDTH {
+                return Some(first_deleted.unwrap_or(current_hash + stop));
+            }
+            current_index += WIDTH;
+        } else {
+            match ctrl[current_hash] {
+                EMPTY => return Some(first_deleted.unwrap_or(current_hash)),
+                DELETED if reuse_deleted && first_deleted.is_none() => {
+                    first_deleted = Some(current_hash);
+                }
+                current_tag if current_tag == tag && slots[current_hash].0 == key => {
+                    return Some(current_hash);
+                }
+                _ => {}
+            }
+            current_index += 1;
+        }
+    }
+    first_deleted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A default-sized map that only grows once every slot is taken, so tests
+    /// can reach a table with no `EMPTY` slot left.
+    fn fillable_map() -> CustomHashMap {
+        CustomHashMap::builder().max_load_factor(1.0).build()
+    }
+
+    #[test]
+    fn when_new_should_be_empty() {
+        let map = CustomHashMap::default();
+        assert_eq!(map.len(), 0);
+        assert!(map.is_empty());
+    }
+
+    #[test]
+    fn when_insert_new_key_should_return_none() {
+        let mut map = CustomHashMap::default();
+        assert_eq!(map.insert(1, 10), None);
+        assert_eq!(map.len(), 1);
+    }
+
+    #[test]
+    fn when_get_existing_key_should_return_value() {
+        let mut map = CustomHashMap::default();
+        map.insert(1, 10);
+        assert_eq!(map.get(1), Some(&10));
+    }
+
+    #[test]
+    fn when_get_nonexistent_key_should_return_none() {
+        let map = CustomHashMap::default();
+        assert_eq!(map.get(1), None);
+    }
+
+    #[test]
+    fn when_insert_existing_key_should_update_and_return_old() {
+        let mut map = CustomHashMap::default();
+        map.insert(1, 10);
+        assert_eq!(map.insert(1, 20), Some(10));
+        assert_eq!(map.get(1), Some(&20));
+    }
+
+    #[test]
+    fn when_hash_collision_should_probe_to_next_slot() {
+        let mut map = CustomHashMap::default();
+        map.insert(1, 10); // hash: 1
+        map.insert(9, 90); // hash: 1, should probe to 2
+        assert_eq!(map.get(1), Some(&10));
+        assert_eq!(map.get(9), Some(&90));
+    }
+
+    #[test]
+    fn when_remove_existing_should_return_value() {
+        let mut map = CustomHashMap::default();
+        map.insert(1, 10);
+        assert_eq!(map.remove(1), Some(10));
+        assert_eq!(map.len(), 0);
+    }
+
+    #[test]
+    fn when_remove_nonexistent_should_return_none() {
+        let mut map = CustomHashMap::default();
+        assert_eq!(map.remove(1), None);
+    }
+
+    #[test]
+    fn when_get_through_deleted_should_find_value() {
+        let mut map = CustomHashMap::default();
+        map.insert(1, 10);
+        map.insert(9, 90);
+        map.remove(1);
+        assert_eq!(map.get(9), Some(&90));
+    }
+
+    #[test]
+    fn when_insert_after_delete_should_reuse_slot() {
+        let mut map = CustomHashMap::default();
+        map.insert(1, 10);
+        map.remove(1);
+        assert_eq!(map.insert(1, 20), None); // treated as new insert
+        assert_eq!(map.len(), 1);
+    }
+
+    #[test]
+    fn when_collision_should_not_update_existing() {
+        let mut map = CustomHashMap::default();
+        map.insert(1, 10);
+        map.insert(9, 90); // collides with 1
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get(1), Some(&10)); // unchanged
+        assert_eq!(map.get(9), Some(&90)); // probed
+    }
+
+    #[test]
+    fn when_collision_insert_should_probe_linearly() {
+        let mut map = CustomHashMap::default();
+        map.insert(1, 10); // hash: 1
+        map.insert(9, 90); // hash: 1, goes to 2
+        map.insert(17, 170); // hash: 1, goes to 3
+
+        assert_eq!(map.get(1), Some(&10));
+        assert_eq!(map.get(9), Some(&90));
+        assert_eq!(map.get(17), Some(&170));
+        assert_eq!(map.len(), 3);
+    }
+
+    #[test]
+    fn when_collision_remove_middle_should_keep_probe_chain() {
+        let mut map = CustomHashMap::default();
+        map.insert(1, 10); // hash: 1
+        map.insert(9, 90); // hash: 1, goes to 2
+        map.insert(17, 170); // hash: 1, goes to 3
+
+        map.remove(9); // middle of chain
+        assert_eq!(map.get(17), Some(&170)); // should still find this
+        assert_eq!(map.len(), 2);
+    }
+
+    #[test]
+    fn when_collision_remove_first_should_keep_probe_chain() {
+        let mut map = CustomHashMap::default();
+        map.insert(1, 10); // hash: 1
+        map.insert(9, 90); // hash: 1, goes to 2
+
+        map.remove(1); // first in chain
+        assert_eq!(map.get(9), Some(&90)); // should still find this
+        assert_eq!(map.len(), 1);
+    }
+
+    #[test]
+    fn when_collision_insert_after_remove_should_reuse_slot() {
+        let mut map = CustomHashMap::default();
+        map.insert(1, 10); // hash: 1
+        map.insert(9, 90); // hash: 1, goes to 2
+        map.remove(1);
+
+        map.insert(17, 170); // hash: 1, should use slot 1
+        assert_eq!(map.get(17), Some(&170));
+        assert_eq!(map.get(9), Some(&90));
+        assert_eq!(map.len(), 2);
+    }
+
+    #[test]
+    fn when_collision_update_should_not_affect_probe_chain() {
+        let mut map = CustomHashMap::default();
+        map.insert(1, 10); // hash: 1
+        map.insert(9, 90); // hash: 1, goes to 2
+
+        assert_eq!(map.insert(1, 100), Some(10)); // update first
+        assert_eq!(map.get(9), Some(&90)); // chain intact
+        assert_eq!(map.len(), 2);
+    }
+
+    #[test]
+    fn when_collision_remove_and_reinsert_should_reuse_first_deleted() {
+        let mut map = CustomHashMap::default();
+        map.insert(1, 10);
+        map.insert(9, 90);
+        map.insert(17, 170);
+        // [Empty, 1, 9, 17]
+
+        map.remove(1);
+        // [Empty, Deleted, 9, 17]
+        map.remove(9);
+        // [Empty, Deleted, Deleted, 17]
+
+        map.insert(25, 250);
+        // [Empty, 25, Deleted, 17]
+
+        assert_eq!(map.get(25), Some(&250));
+        assert_eq!(map.get(17), Some(&170));
+        assert_eq!(map.len(), 2);
+    }
+
+    #[test]
+    fn when_map_full_should_handle_gracefully() {
+        let mut map = fillable_map(); // capacity is 8
+        for i in 0..8 {
+            map.insert(i, i * 10);
+        }
+        assert_eq!(map.len(), 8);
+        assert_eq!(map.get(0), Some(&0));
+        assert_eq!(map.get(7), Some(&70));
+    }
+
+    #[test]
+    fn when_probe_wraps_around_capacity_should_continue_search() {
+        let mut map = CustomHashMap::default();
+        map.insert(7, 70); // hash: 7
+        map.insert(15, 150); // hash: 7, wraps to 0
+        assert_eq!(map.get(15), Some(&150));
+    }
+
+    #[test]
+    fn when_all_slots_deleted_and_get_nonexistent_should_terminate() {
+        let mut map = fillable_map(); // capacity is 8
+                                      // Fill the entire map
+        for i in 0..8 {
+            map.insert(i, i * 10);
+        }
+        // Delete all entries
+        for i in 0..8 {
+            map.remove(i);
+        }
+        // Now all slots are Deleted (no Vacant slots)
+        // Try to get a key that was never in the map
+        assert_eq!(map.get(100), None);
+    }
+
+    // Advanced
+    #[test]
+    fn when_insert_delete_insert_same_hash_sequence_should_work() {
+        let mut map = CustomHashMap::default();
+        // Fill slots 0,1,2
+        map.insert(0, 0); // hash: 0
+        map.insert(8, 8); // hash: 0, probes to 1
+        map.insert(16, 16); // hash: 0, probes to 2
+
+        // Remove middle element
+        map.remove(8);
+        // Remove first element
+        map.remove(0);
+        // Insert new element with same hash
+        map.insert(24, 24); // hash: 0, should reuse first deleted
+
+        assert_eq!(map.get(16), Some(&16)); // Last original still there
+        assert_eq!(map.get(24), Some(&24)); // New insert worked
+        assert_eq!(map.len(), 2);
+    }
+
+    #[test]
+    fn when_wrap_around_with_deletions_should_find_elements() {
+        let mut map = CustomHashMap::default(); // capacity 8
+        map.insert(7, 7); // hash: 7
+        map.insert(15, 15); // hash: 7, wraps to 0
+        map.insert(23, 23); // hash: 7, wraps to 1
+
+        map.remove(15); // Delete middle element
+        assert_eq!(map.get(23), Some(&23)); // Should still find last element
+    }
+
+    #[test]
+    fn when_multiple_hash_collisions_with_interleaved_deletions() {
+        let mut map = CustomHashMap::default();
+        // All these hash to 0
+        map.insert(0, 0); // slot 0
+        map.insert(8, 8); // slot 1
+        map.insert(16, 16); // slot 2
+        map.insert(24, 24); // slot 3
+
+        map.remove(8); // Delete from slot 1
+        map.remove(16); // Delete from slot 2
+
+        assert_eq!(map.get(24), Some(&24)); // Should still find last element
+
+        map.insert(32, 32); // Should reuse first deleted slot (1)
+        assert_eq!(map.get(32), Some(&32));
+    }
+
+    #[test]
+    fn when_insert_at_capacity_boundary() {
+        let mut map = fillable_map(); // capacity 8
+                                      // Fill up to capacity - 1
+        for i in 0..7 {
+            map.insert(i, i);
+        }
+        // Insert at last slot
+        map.insert(7, 7);
+        assert_eq!(map.get(7), Some(&7));
+
+        // Try one more (should handle gracefully even if not optimal)
+        map.insert(8, 8);
+        assert_eq!(map.get(8), Some(&8));
+        assert_eq!(map.len(), 9);
+    }
+
+    #[test]
+    fn when_delete_and_reinsert_at_capacity_boundary() {
+        let mut map = fillable_map();
+        // Fill completely
+        for i in 0..8 {
+            map.insert(i, i);
+        }
+        // Remove last element
+        map.remove(7);
+        // Insert new element that would hash to last slot
+        map.insert(15, 15); // hash: 7
+        assert_eq!(map.get(15), Some(&15));
+    }
+
+    #[test]
+    fn when_long_probe_sequence_with_deletions() {
+        let mut map = CustomHashMap::default();
+        // Create a long probe sequence
+        map.insert(0, 0); // slot 0
+        map.insert(8, 8); // slot 1
+        map.insert(16, 16); // slot 2
+        map.insert(24, 24); // slot 3
+        map.insert(32, 32); // slot 4
+
+        // Delete some middle elements
+        map.remove(8);
+        map.remove(24);
+
+        // Should still find element at end of probe sequence
+        assert_eq!(map.get(32), Some(&32));
+
+        // Insert new element that hashes to 0
+        map.insert(40, 40);
+        assert_eq!(map.get(40), Some(&40));
+    }
+
+    #[test]
+    fn when_remove_all_and_refill_different_order() {
+        let mut map = CustomHashMap::default();
+        // First fill
+        for i in 0..8 {
+            map.insert(i, i);
+        }
+        // Remove all
+        for i in 0..8 {
+            map.remove(i);
+        }
+        // Refill in reverse order
+        for i in (0..8).rev() {
+            map.insert(i, i * 10);
+        }
+
+        assert_eq!(map.len(), 8);
+        // Check all values
+        for i in 0..8 {
+            assert_eq!(map.get(i), Some(&(i * 10)));
+        }
+    }
+
+    #[test]
+    fn when_remove_with_all_slots_deleted_should_terminate() {
+        let mut map = fillable_map();
+        // Fill map
+        for i in 0..8 {
+            map.insert(i, i * 10);
+        }
+        // Delete all but one
+        for i in 0..7 {
+            map.remove(i);
+        }
+        // Try to remove a non-existent key
+        assert_eq!(map.remove(100), None);
+    }
+
+    #[test]
+    fn when_entry_index_colliding_keys_should_land_in_adjacent_slots() {
+        let mut map = CustomHashMap::default();
+        assert_eq!(map.entry_index(1, 10), 1); // hash: 1
+        assert_eq!(map.entry_index(9, 90), 2); // hash: 1, probes to 2
+        assert_eq!(map.entry_index(9, 99), 2); // already present
+        assert_eq!(map.get(9), Some(&90));
+        assert_eq!(map.len(), 2);
+    }
+
+    #[test]
+    fn when_remove_if_predicate_holds_should_remove() {
+        let mut map = CustomHashMap::default();
+        map.insert(1, 10);
+        assert_eq!(map.remove_if(1, |&v| v == 10), Some(10));
+        assert_eq!(map.get(1), None);
+        assert_eq!(map.len(), 0);
+    }
+
+    #[test]
+    fn when_remove_if_predicate_fails_should_keep_entry() {
+        let mut map = CustomHashMap::default();
+        map.insert(1, 10);
+        assert_eq!(map.remove_if(1, |&v| v > 10), None);
+        assert_eq!(map.get(1), Some(&10));
+        assert_eq!(map.len(), 1);
+    }
+
+    #[test]
+    fn when_remove_if_missing_key_should_not_call_predicate() {
+        let mut map = CustomHashMap::default();
+        map.insert(1, 10);
+        assert_eq!(map.remove_if(9, |_| panic!("predicate called")), None);
+        assert_eq!(map.len(), 1);
+    }
+
+    #[test]
+    fn when_drain_sorted_should_yield_ascending_keys_and_empty_map() {
+        let mut map = CustomHashMap::default();
+        for key in [9, 3, 1, 17, 6] {
+            map.insert(key, key * 10);
+        }
+        map.remove(6);
+        let drained: Vec<_> = map.drain_sorted().collect();
+        assert_eq!(drained, vec![(1, 10), (3, 30), (9, 90), (17, 170)]);
+        assert!(map.is_empty());
+        assert_eq!(map.get(9), None);
+    }
+
+    #[test]
+    fn when_drain_sorted_abandoned_early_should_still_empty_map() {
+        let mut map = CustomHashMap::default();
+        map.insert(2, 20);
+        map.insert(1, 10);
+        assert_eq!(map.drain_sorted().next(), Some((1, 10)));
+        assert!(map.is_empty());
+        assert_eq!(map.insert(2, 21), None);
+    }
+
+    #[test]
+    fn when_get_pair_colliding_keys_should_find_both() {
+        let mut map = CustomHashMap::default();
+        map.insert(1, 10); // hash: 1
+        map.insert(9, 90); // hash: 1, probes to 2
+        assert_eq!(map.get_pair(1, 9), (Some(&10), Some(&90)));
+        assert_eq!(map.get_pair(9, 17), (Some(&90), None)); // 17 hashes to 1 too
+        assert_eq!(map.get_pair(9, 9), (Some(&90), Some(&90)));
+        map.insert(17, 170); // hash: 1, probes to 3
+        map.remove(9); // leaves a tombstone in slot 2
+        assert_eq!(map.get_pair(17, 9), (Some(&170), None));
+    }
+
+    #[test]
+    fn when_get_pair_different_buckets_should_look_up_each() {
+        let mut map = CustomHashMap::default();
+        map.insert(1, 10);
+        map.insert(2, 20);
+        assert_eq!(map.get_pair(1, 2), (Some(&10), Some(&20)));
+        assert_eq!(map.get_pair(3, 2), (None, Some(&20)));
+    }
+
+    #[test]
+    fn when_take_existing_should_return_pair_and_remove() {
+        let mut map = CustomHashMap::default();
+        map.insert(1, 10);
+        map.insert(9, 90);
+        assert_eq!(map.take(1), Some((1, 10)));
+        assert_eq!(map.get(1), None);
+        assert_eq!(map.get(9), Some(&90));
+        assert_eq!(map.len(), 1);
+    }
+
+    #[test]
+    fn when_take_absent_should_return_none() {
+        let mut map = CustomHashMap::default();
+        map.insert(1, 10);
+        assert_eq!(map.take(2), None);
+        assert_eq!(map.remove_entry(2), None);
+        assert_eq!(map.len(), 1);
+    }
+
+    #[test]
+    fn when_seeds_differ_should_produce_different_layouts() {
+        let mut first = CustomHashMap::with_seed(8, 1);
+        let mut second = CustomHashMap::with_seed(8, 2);
+        for key in [1, 2, 3] {
+            first.insert(key, key * 10);
+            second.insert(key, key * 10);
+        }
+        assert_ne!(layout(&first), layout(&second));
+        for key in [1, 2, 3] {
+            assert_eq!(first.get(key), Some(&(key * 10)));
+            assert_eq!(second.get(key), Some(&(key * 10)));
+        }
+    }
+
+    #[test]
+    fn when_seeded_should_scatter_keys_that_collide_without_a_seed() {
+        for seed in [1, 99, 123_456_789] {
+            for (capacity, keys) in [(8, [1, 9, 17, 25]), (64, [0, 64, 128, 192])] {
+                let mut plain = CustomHashMap::with_capacity(capacity);
+                let mut seeded = CustomHashMap::with_seed(capacity, seed);
+                for key in keys {
+                    plain.insert(key, key);
+                    seeded.insert(key, key);
+                }
+                assert_eq!(plain.probe_stats().max, 3);
+                let homes: std::collections::HashSet<usize> =
+                    keys.iter().map(|&key| seeded.hash(key)).collect();
+                assert!(homes.len() > 1, "seed {seed} kept {keys:?} in one bucket");
+                assert!(seeded.probe_stats().max < 3, "seed {seed}");
+            }
+        }
+    }
+
+    #[test]
+    fn when_seed_is_zero_should_match_plain_modulo_layout() {
+        let mut seeded = CustomHashMap::with_seed(8, 0);
+        let mut plain = CustomHashMap::with_capacity(8);
+        for key in [1, 9, 17, 4] {
+            seeded.insert(key, key);
+            plain.insert(key, key);
+        }
+        assert_eq!(layout(&seeded), layout(&plain));
+    }
+
+    #[test]
+    fn when_probe_stats_should_report_distances() {
+        let mut map = CustomHashMap::default();
+        assert_eq!(map.probe_stats(), ProbeStats { max: 0, mean: 0.0 });
+        map.insert(1, 10); // hash: 1, distance 0
+        map.insert(9, 90); // hash: 1, distance 1
+        map.insert(17, 170); // hash: 1, distance 2
+        map.insert(7, 70); // hash: 7, distance 0
+        assert_eq!(map.probe_stats(), ProbeStats { max: 2, mean: 0.75 });
+    }
+
+    #[test]
+    fn when_fibonacci_hashing_should_spread_strided_keys() {
+        let build = |hashing| {
+            let mut map = CustomHashMap::builder()
+                .capacity(32)
+                .hashing(hashing)
+                .build();
+            for i in 0..16 {
+                map.insert(i * 16, i);
+            }
+            map
+        };
+        let modulo = build(Hashing::Modulo);
+        let fibonacci = build(Hashing::Fibonacci);
+        // Modulo sends every key to bucket 0 or 16.
+        assert_eq!(modulo.probe_stats().max, 7);
+        assert!(fibonacci.probe_stats().max < modulo.probe_stats().max);
+        for i in 0..16 {
+            assert_eq!(fibonacci.get(i * 16), Some(&i));
+        }
+    }
+
+    #[test]
+    fn when_fibonacci_hashing_sequential_keys_should_not_collide() {
+        let mut map = CustomHashMap::builder()
+            .capacity(32)
+            .hashing(Hashing::Fibonacci)
+            .build();
+        for key in 0..16 {
+            map.insert(key, key);
+        }
+        assert_eq!(map.probe_stats().max, 0);
+    }
+
+    #[test]
+    fn when_split_off_should_partition_keys_without_tombstones() {
+        let mut map = CustomHashMap::default();
+        for key in 0..20 {
+            map.insert(key, key * 2);
+        }
+        let evens = map.split_off(|key| key % 2 == 0);
+        assert_eq!(evens.len(), 10);
+        assert_eq!(map.len(), 10);
+        for key in 0..20 {
+            let (kept, moved) = (map.get(key), evens.get(key));
+            if key % 2 == 0 {
+                assert_eq!((kept, moved), (None, Some(&(key * 2))));
+            } else {
+                assert_eq!((kept, moved), (Some(&(key * 2)), None));
+            }
+        }
+        assert!(!layout(&map).contains(&Slot::Deleted));
+        assert!(!layout(&evens).contains(&Slot::Deleted));
+    }
+
+    #[test]
+    fn when_raw_parts_round_trip_should_rebuild_identical_map() {
+        let mut map = CustomHashMap::default();
+        for (key, value) in [(1, 10), (9, 90), (17, 170), (4, 40)] {
+            map.insert(key, value);
+        }
+        map.remove(9);
+        let expected = layout(&map);
+
+        let (ctrl, slots, size) = map.into_raw_parts();
+        let map = CustomHashMap::from_raw_parts(ctrl, slots, size).unwrap();
+
+        assert_eq!(layout(&map), expected);
+        assert_eq!(map.len(), 3);
+        assert_eq!(map.capacity(), 8);
+        assert_eq!(map.get(17), Some(&170));
+        assert_eq!(map.get(9), None);
+    }
+
+    #[test]
+    fn when_raw_parts_are_inconsistent_should_return_error() {
+        let mut map = CustomHashMap::default();
+        map.insert(9, 90); // hash: 1
+        let (mut ctrl, mut slots, size) = map.into_raw_parts();
+
+        let error = CustomHashMap::from_raw_parts(ctrl.clone(), slots.clone(), size + 1);
+        assert_eq!(
+            error.unwrap_err(),
+            RawPartsError("size != live slot count (2 != 1)".into())
+        );
+        let error = CustomHashMap::from_raw_parts(ctrl.clone(), slots[..4].to_vec(), size);
+        assert_eq!(
+            error.unwrap_err().to_string(),
+            "invalid raw parts: entry slots != capacity (4 != 8)"
+        );
+        ctrl.swap(1, 5);
+        slots.swap(1, 5);
+        let error = CustomHashMap::from_raw_parts(ctrl, slots, size);
+        assert_eq!(
+            error.unwrap_err(),
+            RawPartsError("key 9 unreachable".into())
+        );
+    }
+
+    #[test]
+    fn when_contains_value_present_should_return_true() {
+        let mut map = CustomHashMap::default();
+        map.insert(1, 10);
+        map.insert(9, 90);
+        assert!(map.contains_value(90));
+    }
+
+    #[test]
+    fn when_contains_value_absent_should_return_false() {
+        let mut map = CustomHashMap::default();
+        map.insert(1, 10);
+        assert!(!map.contains_value(1)); // a key, not a value
+    }
+
+    #[test]
+    fn when_contains_value_of_removed_key_should_return_false() {
+        let mut map = CustomHashMap::default();
+        map.insert(1, 10);
+        map.remove(1);
+        assert!(!map.contains_value(10));
+    }
+
+    #[test]
+    fn when_table_grows_should_call_resize_hook() {
+        use std::sync::{Arc, Mutex};
+
+        let resizes = Arc::new(Mutex::new(Vec::new()));
+        let mut map = CustomHashMap::default();
+        let recorded = Arc::clone(&resizes);
+        map.on_resize(move |old, new| recorded.lock().unwrap().push((old, new)));
+        for key in 0..13 {
+            map.insert(key, key);
+        }
+        assert_eq!(*resizes.lock().unwrap(), vec![(8, 16), (16, 32)]);
+        assert_eq!(map.get(12), Some(&12));
+    }
+
+    #[test]
+    fn when_many_deletes_should_keep_probe_load_high_and_live_load_low() {
+        let mut map = CustomHashMap::with_capacity(16);
+        for key in 0..12 {
+            map.insert(key, key);
+        }
+        for key in 0..10 {
+            map.remove(key);
+        }
+        let utilization = map.utilization();
+        assert_eq!(utilization.live_load, 2.0 / 16.0);
+        assert_eq!(utilization.probe_load, 12.0 / 16.0);
+    }
+
+    #[test]
+    fn when_tombstone_reused_should_lower_probe_load() {
+        let mut map = CustomHashMap::default();
+        map.insert(1, 10);
+        map.insert(2, 20);
+        map.remove(1);
+        assert_eq!(map.utilization().probe_load, 2.0 / 8.0);
+        map.insert(9, 90); // hash: 1, reuses the tombstone
+        assert_eq!(
+            map.utilization(),
+            Utilization {
+                live_load: 2.0 / 8.0,
+                probe_load: 2.0 / 8.0
+            }
+        );
+    }
+
+    #[test]
+    fn when_reserve_should_fit_additional_entries_without_growing() {
+        let mut map = CustomHashMap::default();
+        map.insert(1, 10);
+        map.reserve(20);
+        let capacity = map.capacity();
+        assert!(capacity as f64 * 0.75 >= 21.0);
+        for key in 2..22 {
+            map.insert(key, key);
+        }
+        assert_eq!(map.capacity(), capacity);
+        assert_eq!(map.get(1), Some(&10));
+    }
+
+    #[test]
+    fn when_extend_with_exact_size_iterator_should_resize_once() {
+        use std::sync::{Arc, Mutex};
+
+        let resizes = Arc::new(Mutex::new(0));
+        let mut map = CustomHashMap::default();
+        let counter = Arc::clone(&resizes);
+        map.on_resize(move |_, _| *counter.lock().unwrap() += 1);
+        map.extend((0..50).map(|key| (key, key * 2)));
+        assert_eq!(*resizes.lock().unwrap(), 1);
+        assert_eq!(map.len(), 50);
+        for key in 0..50 {
+            assert_eq!(map.get(key), Some(&(key * 2)));
+        }
+    }
+
+    #[test]
+    fn when_extend_hint_exceeds_key_space_should_reserve_at_most_256() {
+        let mut map = CustomHashMap::default();
+        map.extend((0..10_000).map(|i| ((i % 256) as u8, 0)));
+        assert_eq!(map.len(), 256);
+        assert!(map.capacity() < 512);
+    }
+
+    #[test]
+    fn when_pop_until_none_should_drain_map() {
+        let mut map = CustomHashMap::default();
+        let mut inserted = vec![(1, 10), (9, 90), (4, 40), (7, 70)];
+        for &(key, value) in &inserted {
+            map.insert(key, value);
+        }
+        let mut popped = Vec::new();
+        while let Some(entry) = map.pop() {
+            popped.push(entry);
+        }
+        popped.sort();
+        inserted.sort();
+        assert_eq!(popped, inserted);
+        assert!(map.is_empty());
+        assert_eq!(map.pop(), None);
+    }
+
+    #[test]
+    fn when_get_all_should_align_with_input_keys() {
+        let mut map = CustomHashMap::default();
+        map.insert(1, 10);
+        map.insert(9, 90);
+        map.insert(3, 30);
+        assert_eq!(
+            map.get_all(&[9, 2, 1, 9, 17, 3]),
+            vec![Some(90), None, Some(10), Some(90), None, Some(30)]
+        );
+        assert_eq!(map.get_all(&[]), vec![]);
+    }
+
+    #[test]
+    fn when_bump_all_should_count_occurrences() {
+        let mut map = CustomHashMap::default();
+        map.insert(9, 5);
+        map.bump_all(&[1, 1, 1, 9, 17, 1, 9, 17, 17, 2]);
+        assert_eq!(map.get(1), Some(&4));
+        assert_eq!(map.get(9), Some(&7));
+        assert_eq!(map.get(17), Some(&3));
+        assert_eq!(map.get(2), Some(&1));
+        assert_eq!(map.len(), 4);
+    }
+
+    #[test]
+    fn when_bump_all_many_distinct_keys_should_grow_once() {
+        use std::sync::{Arc, Mutex};
+
+        let resizes = Arc::new(Mutex::new(0));
+        let mut map = CustomHashMap::default();
+        let counter = Arc::clone(&resizes);
+        map.on_resize(move |_, _| *counter.lock().unwrap() += 1);
+        let keys: Vec<u8> = (0..=255).chain(0..=255).collect();
+        map.bump_all(&keys);
+        assert_eq!(*resizes.lock().unwrap(), 1);
+        assert!((0..=255).all(|key| map.get(key) == Some(&2)));
+    }
+
+    #[test]
+    fn when_iter_slots_should_expose_full_physical_layout() {
+        let mut map = CustomHashMap::default();
+        map.insert(1, 10); // hash: 1
+        map.insert(9, 90); // hash: 1, probes to 2
+        map.insert(7, 70); // hash: 7
+        map.insert(15, 150); // hash: 7, wraps to 0
+        map.remove(1);
+        let slots: Vec<_> = map.iter_slots().collect();
+        assert_eq!(
+            slots,
+            vec![
+                (
+                    0,
+                    SlotView::Occupied {
+                        key: 15,
+                        value: 150
+                    }
+                ),
+                (1, SlotView::Deleted),
+                (2, SlotView::Occupied { key: 9, value: 90 }),
+                (3, SlotView::Vacant),
+                (4, SlotView::Vacant),
+                (5, SlotView::Vacant),
+                (6, SlotView::Vacant),
+                (7, SlotView::Occupied { key: 7, value: 70 }),
+            ]
+        );
+    }
+
+    #[test]
+    fn when_from_pairs_checked_has_duplicates_should_keep_last() {
+        let map =
+            CustomHashMap::from_pairs_checked(vec![(1, 10), (9, 90), (1, 11), (9, 99), (2, 20)]);
+        assert_eq!(map.len(), 3);
+        assert_eq!(map.get(1), Some(&11));
+        assert_eq!(map.get(9), Some(&99));
+        assert_eq!(map.get(2), Some(&20));
+        map.validate();
+    }
+
+    #[test]
+    fn when_map_is_consistent_should_validate() {
+        let mut map = CustomHashMap::default();
+        for key in 0..20 {
+            map.insert(key, key);
+        }
+        for key in (0..20).step_by(3) {
+            map.remove(key);
+        }
+        map.validate();
+    }
+
+    #[test]
+    #[should_panic(expected = "size != live slot count")]
+    fn when_size_is_wrong_should_fail_validation() {
+        let mut map = CustomHashMap::default();
+        map.insert(1, 10);
+        map.size = 2;
+        map.validate();
+    }
+
+    #[test]
+    #[should_panic(expected = "key 9 unreachable")]
+    fn when_entry_off_its_probe_chain_should_fail_validation() {
+        let mut map = CustomHashMap::default();
+        map.insert(9, 90); // hash: 1
+        map.ctrl.swap(1, 5);
+        map.slots.swap(1, 5);
+        map.validate();
+    }
+
+    #[test]
+    fn when_byte_histogram_should_match_manual_tally() {
+        let data = b"hello, world";
+        let map = CustomHashMap::byte_histogram(data);
+        let mut tally = [0u8; 256];
+        for &byte in data {
+            tally[byte as usize] += 1;
+        }
+        let distinct = tally.iter().filter(|&&count| count > 0).count();
+        assert_eq!(map.len(), distinct);
+        for (byte, &count) in tally.iter().enumerate() {
+            let expected = (count > 0).then_some(count);
+            assert_eq!(map.get(byte as u8).copied(), expected);
+        }
+        assert_eq!(map.get(b'l'), Some(&3));
+    }
+
+    #[test]
+    fn when_retain_compact_should_leave_no_tombstones() {
+        let mut map = CustomHashMap::default();
+        for key in 0..16 {
+            map.insert(key, key * 10);
+        }
+        map.retain_compact(|&key, value| {
+            *value += 1;
+            key % 2 == 0
+        });
+        assert_eq!(map.len(), 8);
+        assert_eq!(map.deleted, 0);
+        for key in 0..16 {
+            let expected = (key % 2 == 0).then_some(key * 10 + 1);
+            assert_eq!(map.get(key).copied(), expected);
+        }
+    }
+
+    #[test]
+    fn when_prefetch_should_not_change_lookups() {
+        let mut map = CustomHashMap::default();
+        map.insert(1, 10);
+        map.insert(9, 90);
+        for key in [1, 9, 200] {
+            map.prefetch(key);
+        }
+        assert_eq!(map.get(9), Some(&90));
+        assert_eq!(map.get(200), None);
+        assert_eq!(map.len(), 2);
+        CustomHashMap::with_capacity(0).prefetch(1);
+    }
+
+    #[test]
+    fn when_insert_tracked_crosses_load_factor_should_report_resize() {
+        let mut map = CustomHashMap::default(); // capacity 8, grows past 6
+        for key in 0..6 {
+            assert_eq!(map.insert_tracked(key, key), (None, false));
+        }
+        assert_eq!(map.insert_tracked(0, 1), (Some(0), false));
+        assert_eq!(map.insert_tracked(6, 6), (None, true));
+        assert_eq!(map.capacity(), 16);
+        assert_eq!(map.insert_tracked(7, 7), (None, false));
+    }
+
+    #[test]
+    fn when_remove_all_should_count_only_present_keys() {
+        let mut map = CustomHashMap::default();
+        for key in [1, 9, 17, 4] {
+            map.insert(key, key);
+        }
+        assert_eq!(map.remove_all(&[9, 100, 4, 9, 200]), 2);
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.get(17), Some(&17));
+        assert_eq!(map.get(9), None);
+        assert_eq!(map.remove_all(&[100]), 0);
+        assert_eq!(map.remove_all(&[1, 17]), 2);
+        assert!(map.is_empty());
+        assert_eq!(map.deleted, 0);
+    }
+
+    #[test]
+    fn when_values_sorted_by_key_should_follow_key_order() {
+        let mut map = CustomHashMap::default();
+        for key in [42, 7, 200, 9, 1, 15] {
+            map.insert(key, key.wrapping_mul(3));
+        }
+        map.remove(9);
+        let values: Vec<u8> = map.values_sorted_by_key().copied().collect();
+        let expected: Vec<u8> = [1u8, 7, 15, 42, 200]
+            .iter()
+            .map(|key| key.wrapping_mul(3))
+            .collect();
+        assert_eq!(values, expected);
+    }
+
+    #[test]
+    fn when_capacity_doubles_memory_usage_should_scale_linearly() {
+        let heap = |capacity| {
+            CustomHashMap::with_capacity(capacity).memory_usage()
+                - std::mem::size_of::<CustomHashMap>()
+        };
+        assert_eq!(heap(64), 64 * 3);
+        assert_eq!(heap(128), 2 * heap(64));
+        assert_eq!(heap(0), 0);
+    }
+
+    #[test]
+    fn when_default_maps_get_same_operations_should_have_identical_layouts() {
+        let build = || {
+            let mut map = CustomHashMap::default();
+            for key in [3, 11, 19, 200, 7, 15] {
+                map.insert(key, key);
+            }
+            map.remove(11);
+            map
+        };
+        let (first, second) = (build(), build());
+        assert!(first.iter_slots().eq(second.iter_slots()));
+        assert_eq!(format!("{first:?}"), format!("{second:?}"));
+    }
+
+    #[test]
+    fn when_insert_or_modify_should_insert_on_miss_and_modify_on_hit() {
+        let mut map = CustomHashMap::default();
+        for key in [1, 9, 1, 1, 9, 4] {
+            map.insert_or_modify(key, 1, |count| *count += 1);
+        }
+        assert_eq!(map.get(1), Some(&3));
+        assert_eq!(map.get(9), Some(&2));
+        assert_eq!(map.get(4), Some(&1));
+        assert_eq!(map.len(), 3);
+    }
+
+    #[test]
+    fn when_keys_matching_should_return_only_satisfying_keys() {
+        let mut map = CustomHashMap::default();
+        for key in 0..20 {
+            map.insert(key, 0);
+        }
+        map.remove(6);
+        let mut keys = map.keys_matching(|key| key % 3 == 0);
+        keys.sort_unstable();
+        assert_eq!(keys, vec![0, 3, 9, 12, 15, 18]);
+    }
+
+    #[test]
+    fn when_bulk_load_is_reserved_should_not_resize_while_loading() {
+        let mut map = CustomHashMap::default();
+        map.reserve(200);
+        assert_eq!(map.resize_count(), 1);
+        for key in 0..200 {
+            map.insert(key, key);
+        }
+        assert_eq!(map.resize_count(), 1);
+
+        let mut unreserved = CustomHashMap::default();
+        for key in 0..200 {
+            unreserved.insert(key, key);
+        }
+        assert_eq!(unreserved.resize_count(), 6);
+
+        let mut presized = CustomHashMap::with_capacity(267);
+        for key in 0..200 {
+            presized.insert(key, key);
+        }
+        assert_eq!(presized.resize_count(), 0);
+    }
+
+    #[test]
+    fn when_entries_mut_should_update_every_live_value() {
+        let mut map = CustomHashMap::default();
+        for key in [1, 9, 17, 40, 100] {
+            map.insert(key, 0);
+        }
+        map.remove(9);
+        for (key, value) in map.entries_mut() {
+            *value = key * 2;
+        }
+        for key in [1, 17, 40, 100] {
+            assert_eq!(map.get(key), Some(&(key * 2)));
+        }
+        assert_eq!(map.get(9), None);
+        assert_eq!(map.len(), 4);
+    }
+
+    #[test]
+    fn when_reserve_for_total_should_fit_total_in_one_power_of_two_resize() {
+        let mut map = CustomHashMap::default();
+        for key in 0..5 {
+            map.insert(key, key);
+        }
+        map.reserve_for_total(200);
+        assert_eq!(map.capacity(), 512); // 256 slots only hold 192
+        assert_eq!(map.resize_count(), 1);
+        for key in 0..200 {
+            map.insert(key, key);
+        }
+        assert_eq!(map.resize_count(), 1);
+        map.reserve_for_total(10);
+        assert_eq!(map.capacity(), 512);
+    }
+
+    #[test]
+    fn when_resizing_disabled_and_full_checked_insert_should_fail() {
+        let mut map = CustomHashMap::builder()
+            .max_load_factor(1.0)
+            .allow_resize(false)
+            .build();
+        for key in 0..8 {
+            assert_eq!(map.checked_insert(key, key), Ok(None));
+        }
+        assert_eq!(map.checked_insert(8, 8), Err(CapacityError));
+        assert_eq!(map.checked_insert(3, 30), Ok(Some(3)));
+        assert_eq!(map.capacity(), 8);
+        assert_eq!(map.len(), 8);
+        assert_eq!(map.get(8), None);
+        assert_eq!(map.get(3), Some(&30));
+        assert_eq!(map.get(7), Some(&7));
+    }
+
+    #[test]
+    #[should_panic(expected = "resizing is disabled")]
+    fn when_resizing_disabled_and_full_insert_should_panic() {
+        let mut map = CustomHashMap::builder().allow_resize(false).build();
+        for key in 0..7 {
+            map.insert(key, key);
+        }
+    }
+
+    #[test]
+    fn when_resizing_disabled_bulk_inserts_should_keep_capacity() {
+        let fixed = || CustomHashMap::builder().allow_resize(false).build();
+        let mut extended = fixed();
+        extended.extend((0..20).map(|i| (i % 4, i)));
+        assert_eq!(extended.capacity(), 8);
+        assert_eq!(extended.len(), 4);
+
+        let mut bumped = fixed();
+        bumped.bump_all(&[1, 2, 3, 1, 2, 3]);
+        bumped.insert_many_sorted(&[(1, 0), (4, 4)]);
+        bumped.collect_into([(5, 5), (6, 6)]);
+        assert_eq!(bumped.capacity(), 8);
+        assert_eq!(bumped.resize_count(), 0);
+
+        let mut shrunk = CustomHashMap::builder()
+            .capacity(64)
+            .shrink_on_remove(true)
+            .allow_resize(false)
+            .build();
+        for key in 0..10 {
+            shrunk.insert(key, key);
+        }
+        shrunk.remove_all(&[0, 1, 2, 3, 4, 5, 6, 7]);
+        shrunk.shrink_to_fit();
+        assert_eq!(shrunk.capacity(), 64);
+        assert_eq!(shrunk.utilization().probe_load, 2.0 / 64.0);
+    }
+
+    #[test]
+    fn when_resizing_disabled_explicit_resizes_should_fail() {
+        let mut map = CustomHashMap::builder().allow_resize(false).build();
+        map.insert(1, 1);
+        assert_eq!(map.try_reserve(20), Err(CapacityError));
+        assert_eq!(map.rebuild_with_capacity(32), Err(CapacityError));
+        assert_eq!(map.rebuild_with_capacity(8), Ok(()));
+        assert_eq!(map.try_reserve(3), Ok(()));
+        assert_eq!(map.capacity(), 8);
+        assert_eq!(map.freeze().len(), 1);
+    }
+
+    #[test]
+    #[should_panic(expected = "resizing is disabled")]
+    fn when_resizing_disabled_reserve_beyond_capacity_should_panic() {
+        let mut map = CustomHashMap::builder().allow_resize(false).build();
+        map.reserve(20);
+    }
+
+    #[test]
+    fn when_values_repeat_should_group_their_keys() {
+        let mut map = CustomHashMap::default();
+        for (key, value) in [(17, 5), (1, 5), (9, 5), (2, 6), (3, 7), (4, 7)] {
+            map.insert(key, value);
+        }
+        assert_eq!(
+            map.duplicate_value_keys(),
+            vec![(5, vec![1, 9, 17]), (7, vec![3, 4])]
+        );
+    }
+
+    #[test]
+    fn when_get_mut_or_insert_default_should_tally_keys() {
+        let mut map = CustomHashMap::default();
+        for key in [3, 11, 3, 19, 3, 11] {
+            *map.get_mut_or_insert_default(key) += 1;
+        }
+        assert_eq!(map.get(3), Some(&3));
+        assert_eq!(map.get(11), Some(&2));
+        assert_eq!(map.get(19), Some(&1));
+        assert_eq!(map.len(), 3);
+    }
+
+    #[test]
+    fn when_probe_chain_exceeds_max_probes_should_grow() {
+        let mut map = CustomHashMap::builder()
+            .max_load_factor(1.0)
+            .max_probes(2)
+            .build();
+        for key in [0, 8, 16] {
+            // All hash to 0 in 8 slots; the third would be 3 probes from home.
+            map.insert(key, key);
+        }
+        assert_eq!(map.capacity(), 16);
+        assert!(map.probe_stats().max < 2);
+        for key in [0, 8, 16] {
+            assert_eq!(map.get(key), Some(&key));
+        }
+    }
+
+    #[test]
+    fn when_probe_chain_exceeds_max_probes_and_resizing_disabled_should_fail() {
+        let mut map = CustomHashMap::builder()
+            .max_load_factor(1.0)
+            .max_probes(2)
+            .allow_resize(false)
+            .build();
+        assert_eq!(map.checked_insert(0, 0), Ok(None));
+        assert_eq!(map.checked_insert(8, 8), Ok(None));
+        assert_eq!(map.checked_insert(16, 16), Err(CapacityError));
+        assert_eq!(map.checked_insert(3, 3), Ok(None));
+        assert_eq!(map.len(), 3);
+        assert_eq!(map.get(16), None);
+    }
+
+    #[test]
+    fn when_converted_to_std_should_have_same_entries() {
+        let mut map = CustomHashMap::default();
+        for key in [1, 9, 17, 4, 200] {
+            map.insert(key, key.wrapping_add(1));
+        }
+        map.remove(9);
+        let std_map = map.to_std_hashmap();
+        assert_eq!(std_map.len(), map.len());
+        for (key, value) in map.live_entries() {
+            assert_eq!(std_map.get(&key), Some(&value));
+        }
+        assert_eq!(HashMap::from(map), std_map);
+    }
+
+    #[test]
+    fn when_std_map_round_trips_should_keep_entries() {
+        let std_map: HashMap<u8, u8> = (0..40).map(|key| (key * 3, key)).collect();
+        let map = CustomHashMap::from(std_map.clone());
+        assert_eq!(map.len(), 40);
+        assert_eq!(map.resize_count(), 1);
+        assert_eq!(map.get(39), Some(&13));
+        assert_eq!(HashMap::from(map), std_map);
+    }
+
+    #[test]
+    fn when_collect_into_reused_should_keep_capacity() {
+        let mut map = CustomHashMap::default();
+        map.collect_into((0..40).map(|key| (key, key)));
+        let capacity = map.capacity();
+        map.collect_into((100..140).map(|key| (key, key + 1)));
+        assert_eq!(map.capacity(), capacity);
+        assert_eq!(map.resize_count(), 1);
+        assert_eq!(map.len(), 40);
+        assert_eq!(map.get(5), None);
+        assert_eq!(map.get(139), Some(&140));
+    }
+
+    #[test]
+    fn when_probe_histogram_should_count_entries_per_distance() {
+        let mut map = CustomHashMap::with_capacity(16);
+        map.insert(1, 0); // slot 1, distance 0
+        map.insert(17, 0); // slot 2, distance 1
+        map.insert(33, 0); // slot 3, distance 2
+        map.insert(2, 0); // slot 4, distance 2
+        map.insert(10, 0); // slot 10, distance 0
+        map.insert(15, 0); // slot 15, distance 0
+        map.insert(31, 0); // wraps to slot 0, distance 1
+        assert_eq!(map.probe_histogram(), vec![3, 2, 2]);
+        assert!(CustomHashMap::default().probe_histogram().is_empty());
+    }
+
+    #[test]
+    fn when_tombstones_crowd_reserve_should_compact_without_growing() {
+        let mut map = CustomHashMap::with_capacity(16); // max load 12
+        for key in 0..12 {
+            map.insert(key, key);
+        }
+        for key in 0..8 {
+            map.remove(key);
+        }
+        map.reserve(6);
+        assert_eq!(map.capacity(), 16);
+        assert_eq!(map.resize_count(), 0);
+        assert_eq!(map.deleted, 0);
+        assert_eq!(map.get(11), Some(&11));
+    }
+
+    #[test]
+    fn when_live_entries_need_room_reserve_should_grow() {
+        let mut map = CustomHashMap::with_capacity(16);
+        for key in 0..12 {
+            map.insert(key, key);
+        }
+        map.remove(0);
+        map.reserve(6);
+        assert!(map.capacity() > 16);
+        assert_eq!(map.resize_count(), 1);
+        assert_eq!(map.deleted, 0);
+    }
+
+    #[test]
+    fn when_get_by_hash_should_find_entry_on_precomputed_chain() {
+        let mut map = CustomHashMap::with_seed(16, 7);
+        for key in [3, 19, 35, 40] {
+            map.insert(key, key + 1);
+        }
+        let hash = map.hash(35);
+        assert_eq!(map.get_by_hash(hash, |key| key == 35), Some(&36));
+        assert_eq!(map.get_by_hash(hash, |key| key == 36), None);
+        assert_eq!(CustomHashMap::default().get_by_hash(5, |_| true), None);
+    }
+
+    #[test]
+    fn when_insert_many_sorted_should_match_naive_inserts() {
+        let mut pairs: Vec<(u8, u8)> = (0..48).map(|i| (i * 5 % 251, i)).collect();
+        let mut sorted = CustomHashMap::with_capacity(64);
+        pairs.sort_by_key(|&(key, _)| sorted.hash(key));
+        sorted.insert_many_sorted(&pairs);
+
+        let mut naive = CustomHashMap::with_capacity(64);
+        for &(key, value) in pairs.iter().rev() {
+            naive.insert(key, value);
+        }
+        assert_eq!(sorted.capacity(), 64);
+        assert_eq!(sorted.to_std_hashmap(), naive.to_std_hashmap());
+    }
+
+    #[test]
+    fn when_key_sits_past_tombstone_insert_should_update_not_duplicate() {
+        for capacity in [8, 32] {
+            let mut map = CustomHashMap::with_capacity(capacity);
+            let keys = [1, 1 + capacity as u8, 1 + 2 * capacity as u8];
+            for key in keys {
+                map.insert(key, 0);
+            }
+            map.remove(keys[0]);
+            assert_eq!(map.insert(keys[2], 1), Some(0));
+            assert_eq!(map.len(), 2);
+            assert_eq!(map.insert(keys[0], 2), None); // reuses the tombstone
+            assert_eq!(map.deleted, 0);
+            map.validate();
+        }
+    }
+
+    #[test]
+    fn when_iterating_snapshot_should_allow_mutating_map() {
+        let mut map = CustomHashMap::default();
+        for key in 0..6 {
+            map.insert(key, key);
+        }
+        let snapshot = map.snapshot();
+        for &(key, value) in &snapshot {
+            map.insert(key + 100, value * 2); // grows the table midway
+            map.remove(key);
+        }
+        assert_eq!(snapshot, (0..6).map(|key| (key, key)).collect::<Vec<_>>());
+        assert_eq!(map.len(), 6);
+        assert_eq!(map.get(105), Some(&10));
+    }
+
+    #[test]
+    fn when_capacity_is_rounded_should_remember_requested_capacity() {
+        let map = CustomHashMap::builder()
+            .capacity(5)
+            .hashing(Hashing::Fibonacci)
+            .build();
+        assert_eq!(map.requested_capacity(), 5);
+        assert_eq!(map.capacity(), 8);
+        let map = CustomHashMap::with_capacity(5);
+        assert_eq!(map.requested_capacity(), 5);
+        assert_eq!(map.capacity(), 5);
+    }
+
+    #[test]
+    fn when_merge_with_should_combine_outer_join_of_keys() {
+        let left = CustomHashMap::from_pairs_checked(vec![(1, 10), (2, 20), (3, 30)]);
+        let right = CustomHashMap::from_pairs_checked(vec![(2, 5), (3, 7), (9, 90)]);
+        let merged = left.merge_with(&right, |a, b| match (a, b) {
+            (Some(a), Some(b)) => Some(a + b),
+            (Some(a), None) => Some(a),
+            (None, Some(b)) => Some(b),
+            (None, None) => unreachable!(),
+        });
+        assert_eq!(
+            merged.to_std_hashmap(),
+            HashMap::from([(1, 10), (2, 25), (3, 37), (9, 90)])
+        );
+        assert_eq!(left.len(), 3);
+        assert_eq!(right.get(2), Some(&5));
+    }
+
+    #[test]
+    fn when_merge_with_union_exceeds_fixed_capacity_should_size_result_for_it() {
+        let fixed = || {
+            CustomHashMap::builder()
+                .max_load_factor(1.0)
+                .allow_resize(false)
+                .build()
+        };
+        let (mut left, mut right) = (fixed(), fixed());
+        for key in 0..6 {
+            left.insert(key, key);
+            right.insert(key + 6, key);
+        }
+        let mut merged = left.merge_with(&right, |a, b| a.or(b));
+        assert_eq!(merged.len(), 12);
+        assert!(merged.capacity() >= 12);
+        assert_eq!(merged.get(11), Some(&5));
+        // The result keeps resizing disabled.
+        assert_eq!(merged.try_reserve(merged.capacity()), Err(CapacityError));
+    }
+
+    #[test]
+    fn when_merge_with_resolver_returns_none_should_drop_key() {
+        let left = CustomHashMap::from_pairs_checked(vec![(1, 10), (2, 20)]);
+        let right = CustomHashMap::from_pairs_checked(vec![(2, 5), (9, 90)]);
+        let both_only = left.merge_with(&right, |a, b| Some(a? + b?));
+        assert_eq!(both_only.to_std_hashmap(), HashMap::from([(2, 25)]));
+    }
+
+    #[cfg(feature = "tracing")]
+    mod tracing_events {
+        use std::sync::{Arc, Mutex};
+
+        use tracing::field::{Field, Visit};
+        use tracing::span::{Attributes, Id, Record};
+        use tracing::{Event, Metadata, Subscriber};
+
+        use super::*;
+
+        /// Records the message of every event it sees.
+        #[derive(Clone, Default)]
+        struct Recorder(Arc<Mutex<Vec<String>>>);
+
+        struct MessageVisitor<'a>(&'a mut String);
+
+        impl Visit for MessageVisitor<'_> {
+            fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+                if field.name() == "message" {
+                    *self.0 = format!("{value:?}");
+                }
+            }
+        }
+
+        impl Subscriber for Recorder {
+            fn enabled(&self, _: &Metadata<'_>) -> bool {
+                true
+            }
+            fn new_span(&self, _: &Attributes<'_>) -> Id {
+                Id::from_u64(1)
+            }
+            fn record(&self, _: &Id, _: &Record<'_>) {}
+            fn record_follows_from(&self, _: &Id, _: &Id) {}
+            fn event(&self, event: &Event<'_>) {
+                let mut message = String::new();
+                event.record(&mut MessageVisitor(&mut message));
+                self.0.lock().unwrap().push(message);
+            }
+            fn enter(&self, _: &Id) {}
+            fn exit(&self, _: &Id) {}
+        }
+
+        #[test]
+        fn when_table_grows_should_emit_resized_event() {
+            let recorder = Recorder::default();
+            tracing::subscriber::with_default(recorder.clone(), || {
+                let mut map = CustomHashMap::default();
+                for key in 0..7 {
+                    map.insert(key, key);
+                }
+                map.remove(3);
+            });
+            let events = recorder.0.lock().unwrap();
+            assert_eq!(events.iter().filter(|event| *event == "resized").count(), 1);
+            assert_eq!(events.iter().filter(|event| *event == "insert").count(), 7);
+            assert_eq!(events.last().map(String::as_str), Some("remove"));
+        }
+    }
+
+    #[test]
+    fn when_at_max_load_try_get_or_insert_with_should_refuse_misses() {
+        let mut map = CustomHashMap::default(); // grows past 6
+        for key in 0..6 {
+            map.insert(key, key);
+        }
+        *map.try_get_or_insert_with(2, || unreachable!()).unwrap() += 10;
+        assert_eq!(map.get(2), Some(&12));
+        assert_eq!(
+            map.try_get_or_insert_with(9, || panic!("called on a refused miss")),
+            Err(CapacityError)
+        );
+        assert_eq!(map.capacity(), 8);
+        assert_eq!(map.len(), 6);
+        map.remove(0);
+        assert_eq!(map.try_get_or_insert_with(9, || 90), Ok(&mut 90));
+    }
+
+    /// Inserts keys `0..count` one at a time and returns the 1-based number of
+    /// each insert that resized the table.
+    fn resizing_inserts(mut map: CustomHashMap, count: u8) -> Vec<u8> {
+        let mut resizing = Vec::new();
+        for key in 0..count {
+            let before = map.resize_count();
+            map.insert(key, key);
+            if map.resize_count() != before {
+                resizing.push(key + 1);
+            }
+        }
+        resizing
+    }
+
+    #[test]
+    fn when_default_map_passes_load_factor_should_resize_on_seventh_insert() {
+        // Capacity 8 at 0.75 holds 6; the next threshold is 12 of 16.
+        assert_eq!(resizing_inserts(CustomHashMap::default(), 13), vec![7, 13]);
+    }
+
+    #[test]
+    fn when_custom_load_factor_should_resize_just_past_threshold() {
+        let map = CustomHashMap::builder().max_load_factor(0.5).build();
+        assert_eq!(resizing_inserts(map, 9), vec![5, 9]);
+    }
+
+    #[test]
+    fn when_partition_by_key_should_split_around_pivot_without_loss() {
+        let mut map = CustomHashMap::default();
+        for key in (0..=255).step_by(5) {
+            map.insert(key, key / 5);
+        }
+        let (low, high) = map.partition_by_key(128);
+        assert!(low.keys_matching(|key| key >= 128).is_empty());
+        assert!(high.keys_matching(|key| key < 128).is_empty());
+        assert_eq!(low.len() + high.len(), map.len());
+        let mut rejoined = low.to_std_hashmap();
+        rejoined.extend(high.to_std_hashmap());
+        assert_eq!(rejoined, map.to_std_hashmap());
+    }
+
+    #[test]
+    fn when_for_each_entry_should_visit_every_live_entry_once() {
+        let mut map = CustomHashMap::default();
+        for key in 0..30 {
+            map.insert(key, key * 2);
+        }
+        map.remove(4);
+        let (mut count, mut sum) = (0, 0u32);
+        map.for_each_entry(|key, &value| {
+            assert_eq!(value, key * 2);
+            count += 1;
+            sum += u32::from(value);
+        });
+        let expected: u32 = map
+            .values_sorted_by_key()
+            .map(|&value| u32::from(value))
+            .sum();
+        assert_eq!((count, sum), (29, expected));
+    }
+
+    #[test]
+    fn when_chains_churn_size_should_track_live_slots() {
+        let mut map = CustomHashMap::builder().max_load_factor(1.0).build();
+        for round in 0..4u8 {
+            for key in (0..64).step_by(8) {
+                map.insert(key, round); // every key hashes to bucket 0
+            }
+            for key in (round * 8..64).step_by(16) {
+                map.remove(key);
+            }
+            map.insert(round * 8, round); // lands past or on a tombstone
+            map.validate();
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "size != live slot count")]
+    fn when_size_desyncs_on_a_bulk_insert_path_should_be_caught() {
+        let mut map = CustomHashMap::default();
+        map.insert(1, 10);
+        map.size = 2;
+        map.bump_all(&[3]);
+    }
+
+    #[test]
+    #[should_panic(expected = "deleted != tombstone count")]
+    fn when_tombstone_count_desyncs_on_a_bulk_remove_should_be_caught() {
+        let mut map = CustomHashMap::default();
+        map.insert(1, 10);
+        map.insert(9, 90); // hash: 1
+        map.deleted = 1;
+        map.remove_all(&[9]);
+    }
+
+    #[test]
+    #[should_panic(expected = "key 1 is stored twice")]
+    fn when_key_is_stored_twice_should_be_caught() {
+        let mut map = CustomHashMap::default();
+        map.insert(1, 10);
+        map.ctrl[5] = h2(1);
+        map.slots[5] = (1, 11);
+        map.size = 2;
+        map.entry_index(3, 30);
+    }
+
+    #[test]
+    fn when_capacity_is_a_const_generic_should_build_that_many_slots() {
+        fn build<const N: usize>() -> CustomHashMap {
+            CustomHashMap::from(DefaultCapacity::<N>)
+        }
+        assert_eq!(build::<16>().capacity(), 16);
+        assert_eq!(CustomHashMap::with_default_capacity::<16>().capacity(), 16);
+    }
+
+    #[test]
+    fn when_isolated_key_removed_should_leave_no_tombstone() {
+        let mut map = CustomHashMap::default();
+        map.insert(3, 30);
+        assert_eq!(map.remove(3), Some(30));
+        assert_eq!(map.deleted, 0);
+        assert_eq!(map.ctrl[3], EMPTY);
+    }
+
+    #[test]
+    fn when_chain_tail_removed_should_clear_trailing_tombstones() {
+        let mut map = CustomHashMap::default();
+        map.insert(1, 10);
+        map.insert(9, 90); // hash: 1, lands in slot 2
+        map.insert(17, 170); // hash: 1, lands in slot 3
+        map.remove(9);
+        assert_eq!(map.deleted, 1);
+        map.remove(17);
+        assert_eq!(map.deleted, 0);
+        assert_eq!(map.get(1), Some(&10));
+        assert_eq!(map.utilization().probe_load, 1.0 / 8.0);
+    }
+
+    #[test]
+    fn when_try_from_iter_all_ok_should_build_map() {
+        let pairs = [(1, 10), (9, 90), (2, 20)].map(Ok::<_, String>);
+        let map = CustomHashMap::try_from_iter(pairs).unwrap();
+        assert_eq!(map.len(), 3);
+        assert_eq!(map.get(1), Some(&10));
+        assert_eq!(map.get(9), Some(&90));
+        assert_eq!(map.get(2), Some(&20));
+    }
+
+    #[test]
+    fn when_try_from_iter_hits_error_should_return_it() {
+        let pairs = "1=10,x=20,3=30".split(',').map(|pair| {
+            let (key, value) = pair.split_once('=').unwrap();
+            Ok((key.parse::<u8>()?, value.parse::<u8>()?))
+        });
+        let result: Result<_, std::num::ParseIntError> = CustomHashMap::try_from_iter(pairs);
+        let error = result.unwrap_err();
+        assert_eq!(*error.kind(), std::num::IntErrorKind::InvalidDigit);
+    }
+
+    #[test]
+    fn when_get_mut_by_indices_distinct_should_update_each_value() {
+        let mut map = CustomHashMap::default();
+        let first = map.entry_index(1, 10);
+        let second = map.entry_index(9, 90);
+        let third = map.entry_index(4, 40);
+        for value in map.get_mut_by_indices(&[third, first, second]).unwrap() {
+            *value += 1;
+        }
+        assert_eq!(map.get(1), Some(&11));
+        assert_eq!(map.get(9), Some(&91));
+        assert_eq!(map.get(4), Some(&41));
+    }
+
+    #[test]
+    fn when_get_mut_by_indices_repeats_index_should_return_none() {
+        let mut map = CustomHashMap::default();
+        let first = map.entry_index(1, 10);
+        let second = map.entry_index(2, 20);
+        assert!(map.get_mut_by_indices(&[first, second, first]).is_none());
+    }
+
+    #[test]
+    fn when_get_mut_by_indices_slot_not_occupied_should_return_none() {
+        let mut map = CustomHashMap::default();
+        let first = map.entry_index(1, 10);
+        let second = map.entry_index(9, 90);
+        map.insert(2, 20);
+        map.remove(9); // slot 2 becomes a tombstone
+        assert!(map.get_mut_by_indices(&[first, second]).is_none());
+        assert!(map.get_mut_by_indices(&[first, 5]).is_none());
+        assert!(map.get_mut_by_indices(&[first, map.capacity()]).is_none());
+        assert_eq!(map.get_mut_by_indices(&[first]), Some(vec![&mut 10]));
+    }
+
+    #[test]
+    fn when_keys_collide_should_report_displaced_entries() {
+        let mut map = CustomHashMap::default();
+        map.insert(1, 10);
+        map.insert(9, 90); // hash: 1, lands in slot 2
+        map.insert(2, 20); // hash: 2, lands in slot 3
+        map.insert(7, 70);
+        map.insert(15, 150); // hash: 7, wraps to slot 0
+        assert_eq!(
+            map.displaced_entries().collect::<Vec<_>>(),
+            vec![(15, 7, 0), (9, 1, 2), (2, 2, 3)]
+        );
+    }
+
+    #[test]
+    fn when_lookups_precomputed_should_read_until_resize() {
+        let mut map = CustomHashMap::default();
+        for key in [1, 9, 4] {
+            map.insert(key, key * 10);
+        }
+        let indices = map.precompute_lookups(&[9, 3, 4]);
+        assert_eq!(indices, vec![Some(2), None, Some(4)]);
+        assert_eq!(map.get_by_index(2), Some((9, &90)));
+        assert_eq!(map.get_by_index(3), None);
+        assert_eq!(map.get_by_index(map.capacity()), None);
+
+        let version = map.version();
+        map.insert(9, 91);
+        assert_eq!(map.version(), version);
+        for key in 10..20 {
+            map.insert(key, key);
+        }
+        assert_ne!(map.version(), version);
+        assert_ne!(
+            map.get_by_index(indices[0].unwrap()),
+            Some((9, map.get(9).unwrap()))
+        );
+    }
+
+    #[test]
+    fn when_drain_range_should_remove_only_keys_in_range() {
+        let mut map = CustomHashMap::with_capacity(512);
+        for key in (0..=255).step_by(5) {
+            map.insert(key, !key);
+        }
+        let mut drained: Vec<(u8, u8)> = map.drain_range(50..=100).collect();
+        drained.sort_unstable();
+        let expected: Vec<(u8, u8)> = (50..=100).step_by(5).map(|key| (key, !key)).collect();
+        assert_eq!(drained, expected);
+        assert_eq!(map.len(), 52 - expected.len());
+        for key in (0..=255).step_by(5) {
+            let expected = (!(50..=100).contains(&key)).then_some(!key);
+            assert_eq!(map.get(key).copied(), expected);
+        }
+    }
+
+    #[test]
+    fn when_drain_range_splits_probe_chain_should_keep_rest_reachable() {
+        let mut map = CustomHashMap::default();
+        map.insert(1, 10);
+        map.insert(9, 90); // hash: 1, lands in slot 2
+        map.insert(17, 170); // hash: 1, lands in slot 3
+        assert_eq!(
+            map.drain_range(0..=9).collect::<Vec<_>>(),
+            vec![(1, 10), (9, 90)]
+        );
+        assert_eq!(map.get(17), Some(&170));
+        assert_eq!(map.len(), 1);
+    }
+
+    #[test]
+    fn when_saturating_bump_past_max_should_stay_at_max() {
+        let mut map = CustomHashMap::default();
+        assert_eq!(map.saturating_bump(3), 1);
+        for _ in 1..300 {
+            map.saturating_bump(3);
+        }
+        assert_eq!(map.get(3), Some(&255));
+        assert_eq!(map.saturating_bump(3), 255);
+        assert_eq!(map.len(), 1);
+    }
+
+    #[test]
+    fn when_dump_layout_should_match_golden_string() {
+        let mut map = CustomHashMap::default();
+        map.insert(1, 10);
+        map.insert(9, 90); // hash: 1, lands in slot 2
+        map.insert(17, 170); // hash: 1, lands in slot 3
+        map.insert(6, 60);
+        map.remove(9);
+        map.remove(6);
+        assert_eq!(map.dump_layout(), "V,O(1:10),D,O(17:170),V,V,V,V");
+        assert_eq!(CustomHashMap::with_capacity(0).dump_layout(), "");
+    }
+
+    #[test]
+    fn when_would_place_at_should_predict_insert_slot() {
+        let mut map = CustomHashMap::default();
+        map.insert(1, 10);
+        map.insert(9, 90); // hash: 1, lands in slot 2
+        map.insert(2, 20); // hash: 2, lands in slot 3
+        assert_eq!(map.would_place_at(9), Some(2));
+        assert_eq!(map.would_place_at(17), Some(4));
+        assert_eq!(map.entry_index(17, 170), 4);
+
+        map.remove(9);
+        assert_eq!(map.would_place_at(25), Some(2));
+        assert_eq!(map.entry_index(25, 250), 2);
+        assert_eq!(CustomHashMap::with_capacity(0).would_place_at(1), None);
+    }
+
+    #[test]
+    fn when_shrink_to_fit_nearly_empty_should_stop_at_min_capacity() {
+        let mut map = CustomHashMap::with_capacity(256);
+        map.shrink_to_fit();
+        assert_eq!(map.capacity(), 8);
+
+        let mut map = CustomHashMap::with_capacity(256);
+        map.set_min_capacity(32);
+        map.insert(1, 10);
+        map.shrink_to_fit();
+        assert_eq!(map.capacity(), 32);
+        assert_eq!(map.get(1), Some(&10));
+        map.remove(1);
+        map.shrink_to_fit();
+        assert_eq!(map.capacity(), 32);
+    }
+
+    #[test]
+    fn when_shrink_to_fit_should_keep_entries_within_max_load() {
+        let mut map = CustomHashMap::with_capacity(256);
+        for key in 0..30 {
+            map.insert(key, key);
+        }
+        map.remove(0);
+        map.shrink_to_fit();
+        assert_eq!(map.capacity(), 39); // 29 / 0.75, rounded up
+        assert_eq!(map.deleted, 0);
+        assert!((1..30).all(|key| map.get(key) == Some(&key)));
+    }
+
+    #[test]
+    fn when_auto_shrinking_should_respect_min_capacity() {
+        let mut map = CustomHashMap::builder()
+            .capacity(64)
+            .shrink_on_remove(true)
+            .build();
+        map.set_min_capacity(16);
+        for key in 0..40 {
+            map.insert(key, key);
+        }
+        for key in 0..40 {
+            map.remove(key);
+        }
+        assert_eq!(map.capacity(), 16);
     }
 
     #[test]
-    fn when_remove_nonexistent_should_return_none() {
+    fn when_compare_and_swap_matches_should_swap() {
         let mut map = CustomHashMap::default();
-        assert_eq!(map.remove(1), None);
+        map.insert(1, 10);
+        assert_eq!(map.compare_and_swap(1, 10, 11), Ok(10));
+        assert_eq!(map.get(1), Some(&11));
     }
 
     #[test]
-    fn when_get_through_deleted_should_find_value() {
+    fn when_compare_and_swap_mismatches_should_return_current() {
         let mut map = CustomHashMap::default();
         map.insert(1, 10);
-        map.insert(9, 90);
-        map.remove(1);
-        assert_eq!(map.get(9), Some(&90));
+        assert_eq!(map.compare_and_swap(1, 9, 11), Err(Some(10)));
+        assert_eq!(map.get(1), Some(&10));
     }
 
     #[test]
-    fn when_insert_after_delete_should_reuse_slot() {
+    fn when_compare_and_swap_key_absent_should_return_none() {
         let mut map = CustomHashMap::default();
         map.insert(1, 10);
-        map.remove(1);
-        assert_eq!(map.insert(1, 20), None); // treated as new insert
+        assert_eq!(map.compare_and_swap(9, 10, 11), Err(None));
+        assert_eq!(map.get(9), None);
         assert_eq!(map.len(), 1);
     }
 
     #[test]
-    fn when_collision_should_not_update_existing() {
+    fn when_rebuild_with_capacity_should_grow_and_shrink() {
         let mut map = CustomHashMap::default();
-        map.insert(1, 10);
-        map.insert(9, 90); // collides with 1
-        assert_eq!(map.len(), 2);
-        assert_eq!(map.get(1), Some(&10)); // unchanged
-        assert_eq!(map.get(9), Some(&90)); // probed
+        for key in 0..6 {
+            map.insert(key, key * 10);
+        }
+        map.remove(0);
+        assert_eq!(map.rebuild_with_capacity(100), Ok(()));
+        assert_eq!(map.capacity(), 100);
+        assert_eq!(map.deleted, 0);
+        assert_eq!(map.rebuild_with_capacity(7), Ok(()));
+        assert_eq!(map.capacity(), 7);
+        assert!((1..6).all(|key| map.get(key) == Some(&(key * 10))));
+        assert_eq!(map.len(), 5);
     }
 
     #[test]
-    fn when_collision_insert_should_probe_linearly() {
+    fn when_rebuild_with_capacity_too_small_should_fail_unchanged() {
         let mut map = CustomHashMap::default();
-        map.insert(1, 10); // hash: 1
-        map.insert(9, 90); // hash: 1, goes to 2
-        map.insert(17, 170); // hash: 1, goes to 3
+        for key in 0..6 {
+            map.insert(key, key);
+        }
+        assert_eq!(map.rebuild_with_capacity(7), Err(CapacityError));
+        assert_eq!(map.capacity(), 8);
+        assert_eq!(map.len(), 6);
+    }
 
-        assert_eq!(map.get(1), Some(&10));
-        assert_eq!(map.get(9), Some(&90));
-        assert_eq!(map.get(17), Some(&170));
-        assert_eq!(map.len(), 3);
+    #[test]
+    fn when_rebuild_with_capacity_under_fibonacci_should_round_to_power_of_two() {
+        let mut map = CustomHashMap::builder().hashing(Hashing::Fibonacci).build();
+        map.insert(200, 1);
+        assert_eq!(map.rebuild_with_capacity(20), Ok(()));
+        assert_eq!(map.capacity(), 32);
+        assert_eq!(map.get(200), Some(&1));
     }
 
     #[test]
-    fn when_collision_remove_middle_should_keep_probe_chain() {
+    fn when_modify_or_insert_should_modify_hit_and_insert_miss() {
         let mut map = CustomHashMap::default();
-        map.insert(1, 10); // hash: 1
-        map.insert(9, 90); // hash: 1, goes to 2
-        map.insert(17, 170); // hash: 1, goes to 3
-
-        map.remove(9); // middle of chain
-        assert_eq!(map.get(17), Some(&170)); // should still find this
+        map.insert(1, 10);
+        map.modify_or_insert(1, |value| *value += 5, 0);
+        map.modify_or_insert(9, |_| panic!("key 9 is absent"), 90);
+        assert_eq!(map.get(1), Some(&15));
+        assert_eq!(map.get(9), Some(&90));
         assert_eq!(map.len(), 2);
+        let version = map.version();
+        map.modify_or_insert(9, |value| *value += 1, 0);
+        assert_eq!(map.version(), version);
+        assert_eq!(map.get(9), Some(&91));
     }
 
     #[test]
-    fn when_collision_remove_first_should_keep_probe_chain() {
+    fn when_insert_measured_colliding_keys_should_report_collision_depth() {
         let mut map = CustomHashMap::default();
-        map.insert(1, 10); // hash: 1
-        map.insert(9, 90); // hash: 1, goes to 2
-
-        map.remove(1); // first in chain
-        assert_eq!(map.get(9), Some(&90)); // should still find this
-        assert_eq!(map.len(), 1);
+        assert_eq!(map.insert_measured(1, 10), (None, 1));
+        assert_eq!(map.insert_measured(9, 90), (None, 2)); // hash: 1
+        assert_eq!(map.insert_measured(17, 170), (None, 3)); // hash: 1
+        assert_eq!(map.insert_measured(2, 20), (None, 3)); // hash: 2, pushed past 9 and 17
+        assert_eq!(map.insert_measured(9, 91), (Some(90), 2));
+        assert_eq!(map.insert_measured(7, 70), (None, 1));
+        assert_eq!(map.insert_measured(15, 150), (None, 2)); // hash: 7, wraps to 0
     }
 
     #[test]
-    fn when_collision_insert_after_remove_should_reuse_slot() {
+    fn when_tally_should_count_keys_and_report_new_ones() {
         let mut map = CustomHashMap::default();
-        map.insert(1, 10); // hash: 1
-        map.insert(9, 90); // hash: 1, goes to 2
-        map.remove(1);
-
-        map.insert(17, 170); // hash: 1, should use slot 1
-        assert_eq!(map.get(17), Some(&170));
-        assert_eq!(map.get(9), Some(&90));
-        assert_eq!(map.len(), 2);
+        map.insert(7, 2);
+        let created = map.tally("abracadabra".bytes().chain([7, 7]));
+        assert_eq!(created, 5);
+        assert_eq!(map.get(b'a'), Some(&5));
+        assert_eq!(map.get(b'b'), Some(&2));
+        assert_eq!(map.get(b'r'), Some(&2));
+        assert_eq!(map.get(b'c'), Some(&1));
+        assert_eq!(map.get(b'd'), Some(&1));
+        assert_eq!(map.get(7), Some(&4));
+        assert_eq!(map.tally([b'a', 9]), 1);
     }
 
     #[test]
-    fn when_collision_update_should_not_affect_probe_chain() {
+    fn when_scripted_operations_should_match_std_hashmap() {
         let mut map = CustomHashMap::default();
-        map.insert(1, 10); // hash: 1
-        map.insert(9, 90); // hash: 1, goes to 2
-
-        assert_eq!(map.insert(1, 100), Some(10)); // update first
-        assert_eq!(map.get(9), Some(&90)); // chain intact
-        assert_eq!(map.len(), 2);
+        let mut reference = HashMap::new();
+        for key in 0..36u8 {
+            map.insert(key * 7, key);
+            reference.insert(key * 7, key);
+            if key % 3 == 0 {
+                map.remove(key * 5);
+                reference.remove(&(key * 5));
+            }
+            map.assert_matches(&reference);
+        }
+        map.retain_compact(|&key, _| key % 2 == 0);
+        reference.retain(|&key, _| key % 2 == 0);
+        map.assert_matches(&reference);
     }
 
     #[test]
-    fn when_collision_remove_and_reinsert_should_reuse_first_deleted() {
+    #[should_panic(expected = "(len 2 != 2): 2: None != Some(20), 9: Some(90) != None")]
+    fn when_map_differs_from_reference_should_panic_with_diff() {
         let mut map = CustomHashMap::default();
         map.insert(1, 10);
         map.insert(9, 90);
-        map.insert(17, 170);
-        // [Empty, 1, 9, 17]
-
-        map.remove(1);
-        // [Empty, Deleted, 9, 17]
-        map.remove(9);
-        // [Empty, Deleted, Deleted, 17]
-
-        map.insert(25, 250);
-        // [Empty, 25, Deleted, 17]
-
-        assert_eq!(map.get(25), Some(&250));
-        assert_eq!(map.get(17), Some(&170));
-        assert_eq!(map.len(), 2);
+        map.assert_matches(&HashMap::from([(1, 10), (2, 20)]));
     }
 
     #[test]
-    fn when_map_full_should_handle_gracefully() {
-        let mut map = CustomHashMap::default(); // capacity is 8
-        for i in 0..8 {
-            map.insert(i, i * 10);
+    fn when_reinserting_after_deletes_should_reuse_slots_without_growing() {
+        let mut map = CustomHashMap::default();
+        for round in 0..20u8 {
+            for key in 0..6 {
+                map.insert(key * 8 + round % 3, key);
+            }
+            for key in 0..6 {
+                map.remove(key * 8 + round % 3);
+            }
         }
-        assert_eq!(map.len(), 8);
-        assert_eq!(map.get(0), Some(&0));
-        assert_eq!(map.get(7), Some(&70));
+        assert_eq!(map.resize_count(), 0);
+        assert_eq!(map.capacity(), 8);
+        assert!(map.is_empty());
     }
 
     #[test]
-    fn when_probe_wraps_around_capacity_should_continue_search() {
+    fn when_tombstones_crowd_insert_should_compact_without_growing() {
         let mut map = CustomHashMap::default();
-        map.insert(7, 70); // hash: 7
-        map.insert(15, 150); // hash: 7, wraps to 0
-        assert_eq!(map.get(15), Some(&150));
-    }
-
-    #[test]
-    fn when_all_slots_deleted_and_get_nonexistent_should_terminate() {
-        let mut map = CustomHashMap::default(); // capacity is 8
-                                                // Fill the entire map
-        for i in 0..8 {
-            map.insert(i, i * 10);
+        for key in 0..6 {
+            map.insert(key, key);
         }
-        // Delete all entries
-        for i in 0..8 {
-            map.remove(i);
+        for key in 0..5 {
+            map.remove(key);
         }
-        // Now all slots are Deleted (no Vacant slots)
-        // Try to get a key that was never in the map
-        assert_eq!(map.get(100), None);
+        assert_eq!(map.deleted, 5);
+        map.insert(6, 6);
+        map.insert(14, 14); // hash: 6, skips the tombstones to a fresh slot
+        assert_eq!(map.resize_count(), 0);
+        assert_eq!(map.deleted, 0);
+        assert!([5, 6, 14].iter().all(|key| map.get(*key) == Some(key)));
     }
 
-    // Advanced
     #[test]
-    fn when_insert_delete_insert_same_hash_sequence_should_work() {
+    fn when_keys_collide_should_report_cluster_run() {
         let mut map = CustomHashMap::default();
-        // Fill slots 0,1,2
-        map.insert(0, 0); // hash: 0
-        map.insert(8, 8); // hash: 0, probes to 1
-        map.insert(16, 16); // hash: 0, probes to 2
-
-        // Remove middle element
-        map.remove(8);
-        // Remove first element
-        map.remove(0);
-        // Insert new element with same hash
-        map.insert(24, 24); // hash: 0, should reuse first deleted
-
-        assert_eq!(map.get(16), Some(&16)); // Last original still there
-        assert_eq!(map.get(24), Some(&24)); // New insert worked
-        assert_eq!(map.len(), 2);
+        map.insert(1, 10);
+        map.insert(9, 90); // hash: 1, lands in slot 2
+        map.insert(17, 170); // hash: 1, lands in slot 3
+        map.insert(5, 50);
+        assert_eq!(map.cluster_runs(), vec![(1, 3), (5, 1)]);
+        map.insert(25, 250); // hash: 1, lands in slot 4 and joins the runs
+        map.remove(9);
+        assert_eq!(map.cluster_runs(), vec![(1, 5)]);
     }
 
     #[test]
-    fn when_wrap_around_with_deletions_should_find_elements() {
-        let mut map = CustomHashMap::default(); // capacity 8
-        map.insert(7, 7); // hash: 7
-        map.insert(15, 15); // hash: 7, wraps to 0
-        map.insert(23, 23); // hash: 7, wraps to 1
-
-        map.remove(15); // Delete middle element
-        assert_eq!(map.get(23), Some(&23)); // Should still find last element
+    fn when_run_wraps_around_should_report_it_once() {
+        let mut map = CustomHashMap::default();
+        map.insert(7, 70);
+        map.insert(15, 150); // hash: 7, wraps to slot 0
+        map.insert(3, 30);
+        assert_eq!(map.cluster_runs(), vec![(3, 1), (7, 2)]);
+        assert_eq!(CustomHashMap::with_capacity(0).cluster_runs(), vec![]);
     }
 
     #[test]
-    fn when_multiple_hash_collisions_with_interleaved_deletions() {
+    fn when_get_or_should_borrow_stored_value_or_default() {
         let mut map = CustomHashMap::default();
-        // All these hash to 0
-        map.insert(0, 0); // slot 0
-        map.insert(8, 8); // slot 1
-        map.insert(16, 16); // slot 2
-        map.insert(24, 24); // slot 3
+        map.insert(1, 10);
+        let default = 0;
+        assert!(std::ptr::eq(map.get_or(1, &default), map.get(1).unwrap()));
+        assert!(std::ptr::eq(map.get_or(9, &default), &default));
+        assert_eq!(*map.get_or(1, &default), 10);
+    }
 
-        map.remove(8); // Delete from slot 1
-        map.remove(16); // Delete from slot 2
+    #[test]
+    fn when_capacity_policy_grows_by_fixed_step_should_follow_it() {
+        use std::sync::{Arc, Mutex};
 
-        assert_eq!(map.get(24), Some(&24)); // Should still find last element
+        #[derive(Debug)]
+        struct StepPolicy;
 
-        map.insert(32, 32); // Should reuse first deleted slot (1)
-        assert_eq!(map.get(32), Some(&32));
-    }
+        impl CapacityPolicy for StepPolicy {
+            fn should_grow(&self, _size: usize, capacity: usize) -> Option<usize> {
+                Some(capacity + 16)
+            }
 
-    #[test]
-    fn when_insert_at_capacity_boundary() {
-        let mut map = CustomHashMap::default(); // capacity 8
-                                                // Fill up to capacity - 1
-        for i in 0..7 {
-            map.insert(i, i);
+            fn should_shrink(&self, _size: usize, _capacity: usize) -> Option<usize> {
+                None
+            }
         }
-        // Insert at last slot
-        map.insert(7, 7);
-        assert_eq!(map.get(7), Some(&7));
 
-        // Try one more (should handle gracefully even if not optimal)
-        map.insert(8, 8);
+        let mut map = CustomHashMap::builder()
+            .shrink_on_remove(true)
+            .capacity_policy(StepPolicy)
+            .build();
+        let resizes = Arc::new(Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&resizes);
+        map.on_resize(move |old, new| recorded.lock().unwrap().push((old, new)));
+        for key in 0..30 {
+            map.insert(key, key);
+        }
+        assert_eq!(*resizes.lock().unwrap(), vec![(8, 24), (24, 40)]);
+        for key in 0..30 {
+            map.remove(key);
+        }
+        assert_eq!(map.capacity(), 40);
     }
 
     #[test]
-    fn when_delete_and_reinsert_at_capacity_boundary() {
+    fn when_default_policy_should_double_and_never_shrink() {
         let mut map = CustomHashMap::default();
-        // Fill completely
-        for i in 0..8 {
-            map.insert(i, i);
+        assert!(format!("{map:?}").contains("capacity_policy: DoublingPolicy"));
+        for key in 0..30 {
+            map.insert(key, key);
         }
-        // Remove last element
-        map.remove(7);
-        // Insert new element that would hash to last slot
-        map.insert(15, 15); // hash: 7
-        assert_eq!(map.get(15), Some(&15));
+        assert_eq!(map.capacity(), 64);
+        for key in 0..28 {
+            map.remove(key);
+        }
+        assert_eq!(map.capacity(), 64);
+        assert_eq!(map.get(29), Some(&29));
     }
 
     #[test]
-    fn when_long_probe_sequence_with_deletions() {
+    fn when_growth_factor_policy_shrinks_when_sparse_should_halve() {
         let mut map = CustomHashMap::default();
-        // Create a long probe sequence
-        map.insert(0, 0); // slot 0
-        map.insert(8, 8); // slot 1
-        map.insert(16, 16); // slot 2
-        map.insert(24, 24); // slot 3
-        map.insert(32, 32); // slot 4
+        map.set_capacity_policy(GrowthFactorPolicy {
+            factor: 2.0,
+            shrink_when_sparse: true,
+        });
+        for key in 0..30 {
+            map.insert(key, key);
+        }
+        assert_eq!(map.capacity(), 64);
+        for key in 0..28 {
+            map.remove(key);
+        }
+        assert_eq!(map.capacity(), 8);
+        assert_eq!(map.get(29), Some(&29));
+        let split = map.split_off(|key| key == 29);
+        assert!(format!("{split:?}").contains("shrink_when_sparse: true"));
+    }
 
-        // Delete some middle elements
-        map.remove(8);
-        map.remove(24);
+    /// The original enum-per-slot implementation, kept as a reference model
+    /// for the control-byte layout.
+    mod reference {
+        #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+        pub enum Slot {
+            Vacant,
+            Deleted,
+            Occupied { key: u8, value: u8 },
+        }
 
-        // Should still find element at end of probe sequence
-        assert_eq!(map.get(32), Some(&32));
+        pub struct EnumHashMap {
+            pub entries: Vec<Slot>,
+            size: usize,
+            capacity: usize,
+        }
 
-        // Insert new element that hashes to 0
-        map.insert(40, 40);
-        assert_eq!(map.get(40), Some(&40));
-    }
+        impl EnumHashMap {
+            pub fn with_capacity(capacity: usize) -> Self {
+                Self {
+                    entries: vec![Slot::Vacant; capacity],
+                    size: 0,
+                    capacity,
+                }
+            }
 
-    #[test]
-    fn when_remove_all_and_refill_different_order() {
-        let mut map = CustomHashMap::default();
-        // First fill
-        for i in 0..8 {
-            map.insert(i, i);
+            pub fn insert(&mut self, key: u8, value: u8) -> Option<u8> {
+                let mut free = None;
+                for current_index in 0..self.capacity {
+                    let current_hash = (key as usize + current_index) % self.capacity;
+                    match self.entries[current_hash] {
+                        Slot::Vacant => {
+                            free = free.or(Some(current_hash));
+                            break;
+                        }
+                        Slot::Deleted => free = free.or(Some(current_hash)),
+                        Slot::Occupied {
+                            key: current_key,
+                            value: current_value,
+                        } if current_key == key => {
+                            self.entries[current_hash] = Slot::Occupied { key, value };
+                            return Some(current_value);
+                        }
+                        Slot::Occupied { .. } => {}
+                    }
+                }
+                if let Some(index) = free {
+                    self.entries[index] = Slot::Occupied { key, value };
+                    self.size += 1;
+                }
+                None
+            }
+
+            pub fn get(&self, key: u8) -> Option<&u8> {
+                for current_index in 0..self.capacity {
+                    let current_hash = (key as usize + current_index) % self.capacity;
+                    match &self.entries[current_hash] {
+                        Slot::Vacant => return None,
+                        Slot::Occupied {
+                            key: current_key,
+                            value,
+                        } if *current_key == key => return Some(value),
+                        _ => {}
+                    }
+                }
+                None
+            }
+
+            pub fn remove(&mut self, key: u8) -> Option<u8> {
+                for current_index in 0..self.capacity {
+                    let current_hash = (key as usize + current_index) % self.capacity;
+                    match self.entries[current_hash] {
+                        Slot::Vacant => return None,
+                        Slot::Occupied {
+                            key: current_key,
+                            value,
+                        } if current_key == key => {
+                            self.size -= 1;
+                            let next = (current_hash + 1) % self.capacity;
+                            if !matches!(self.entries[next], Slot::Vacant) {
+                                self.entries[current_hash] = Slot::Deleted;
+                                return Some(value);
+                            }
+                            self.entries[current_hash] = Slot::Vacant;
+                            let mut previous = (current_hash + self.capacity - 1) % self.capacity;
+                            while matches!(self.entries[previous], Slot::Deleted) {
+                                self.entries[previous] = Slot::Vacant;
+                                previous = (previous + self.capacity - 1) % self.capacity;
+                            }
+                            return Some(value);
+                        }
+                        _ => {}
+                    }
+                }
+                None
+            }
+
+            pub fn len(&self) -> usize {
+                self.size
+            }
         }
-        // Remove all
-        for i in 0..8 {
-            map.remove(i);
+    }
+
+    use reference::{EnumHashMap, Slot};
+
+    fn layout(map: &CustomHashMap) -> Vec<Slot> {
+        map.ctrl
+            .iter()
+            .zip(&map.slots)
+            .map(|(&ctrl, &(key, value))| match ctrl {
+                EMPTY => Slot::Vacant,
+                DELETED => Slot::Deleted,
+                _ => Slot::Occupied { key, value },
+            })
+            .collect()
+    }
+
+    /// Small deterministic xorshift generator so randomized tests are
+    /// reproducible without pulling in a dependency.
+    pub(crate) struct XorShift(pub(crate) u64);
+
+    impl XorShift {
+        pub(crate) fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
         }
-        // Refill in reverse order
-        for i in (0..8).rev() {
-            map.insert(i, i * 10);
+
+        pub(crate) fn next_u8(&mut self, below: u8) -> u8 {
+            (self.next() % below as u64) as u8
         }
+    }
 
-        assert_eq!(map.len(), 8);
-        // Check all values
-        for i in 0..8 {
-            assert_eq!(map.get(i), Some(&(i * 10)));
+    #[cfg(all(feature = "simd", target_arch = "x86_64", target_feature = "sse2"))]
+    #[test]
+    fn when_random_operations_should_behave_the_same_with_simd_and_scalar_groups() {
+        use group::with_scalar_groups;
+
+        for seed in 1..=16 {
+            let mut rng = XorShift(seed);
+            for capacity in [16, 40, 100, 256] {
+                let mut simd = CustomHashMap::with_capacity(capacity);
+                let mut scalar = CustomHashMap::with_capacity(capacity);
+                for _ in 0..1024 {
+                    let key = rng.next_u8(200);
+                    match rng.next() % 3 {
+                        0 => {
+                            let value = rng.next() as u8;
+                            let expected = with_scalar_groups(|| scalar.insert(key, value));
+                            assert_eq!(simd.insert(key, value), expected);
+                        }
+                        1 => {
+                            let expected = with_scalar_groups(|| scalar.get(key).copied());
+                            assert_eq!(simd.get(key).copied(), expected);
+                        }
+                        _ => {
+                            let expected = with_scalar_groups(|| scalar.remove(key));
+                            assert_eq!(simd.remove(key), expected);
+                        }
+                    }
+                }
+                assert_eq!(simd.dump_layout(), scalar.dump_layout());
+            }
         }
     }
 
     #[test]
-    fn when_remove_with_all_slots_deleted_should_terminate() {
+    fn when_collisions_and_deletions_should_match_enum_layout() {
         let mut map = CustomHashMap::default();
-        // Fill map
-        for i in 0..8 {
-            map.insert(i, i * 10);
+        let mut reference = EnumHashMap::with_capacity(8);
+        for (key, value) in [(1, 10), (9, 90), (17, 170), (7, 70), (15, 150)] {
+            map.insert(key, value);
+            reference.insert(key, value);
         }
-        // Delete all but one
-        for i in 0..7 {
-            map.remove(i);
+        map.remove(9);
+        reference.remove(9);
+        // [15, 1, Deleted, 17, Empty, Empty, Empty, 7]
+        assert_eq!(layout(&map), reference.entries);
+    }
+
+    #[test]
+    fn when_random_operations_should_behave_like_enum_implementation() {
+        for seed in 1..=64 {
+            let mut rng = XorShift(seed);
+            for capacity in [1, 3, 8, 17, 40, 100] {
+                let mut map = CustomHashMap::builder()
+                    .capacity(capacity)
+                    .max_load_factor(1.0)
+                    .build();
+                let mut reference = EnumHashMap::with_capacity(capacity);
+                for _ in 0..512 {
+                    let key = rng.next_u8(120);
+                    let value = rng.next() as u8;
+                    // The reference model cannot grow, so keep it from filling up.
+                    let fits = reference.len() < capacity || reference.get(key).is_some();
+                    match rng.next() % 3 {
+                        0 if fits => {
+                            assert_eq!(map.insert(key, value), reference.insert(key, value))
+                        }
+                        1 => assert_eq!(map.get(key), reference.get(key)),
+                        _ => assert_eq!(map.remove(key), reference.remove(key)),
+                    }
+                    assert_eq!(map.len(), reference.len());
+                }
+                assert_eq!(layout(&map), reference.entries);
+                let tombstones = reference
+                    .entries
+                    .iter()
+                    .filter(|&&slot| slot == Slot::Deleted);
+                assert_eq!(map.deleted, tombstones.count());
+            }
         }
-        // Try to remove a non-existent key
-        assert_eq!(map.remove(100), None);
     }
 }
@@ -6,22 +6,103 @@ use rust_open_addresing_linear_probing::CustomHashMap;
 fn main() {
     let args: Vec<String> = std::env::args().collect();
     let use_custom = args.iter().any(|arg| arg == "--custom");
+    let use_enum = args.iter().any(|arg| arg == "--enum");
+    let verify = args.iter().any(|arg| arg == "--verify");
     let capacity = args
         .iter()
         .position(|arg| arg == "--capacity")
         .and_then(|i| args.get(i + 1))
         .and_then(|n| n.parse::<usize>().ok())
         .expect("Capacity is required");
+    let prefill_percent = args
+        .iter()
+        .position(|arg| arg == "--prefill")
+        .and_then(|i| args.get(i + 1))
+        .map(|n| n.parse::<usize>().expect("Prefill must be a percentage"))
+        .unwrap_or(0);
+    let pattern = args
+        .iter()
+        .position(|arg| arg == "--pattern")
+        .and_then(|i| args.get(i + 1))
+        .map(|name| Pattern::parse(name).expect("Pattern must be sequential or random"))
+        .unwrap_or(Pattern::Sequential);
 
+    if verify {
+        let mut map = Lockstep::with_capacity(capacity);
+        let prefilled = prefill(&mut map, capacity, prefill_percent, pattern);
+        bench("verify", &mut map, capacity, &prefilled);
+        return;
+    }
+
+    assert!(capacity > 1000, "Capacity must be greater than 1000");
     if use_custom {
         let mut map = CustomHashMap::with_capacity(capacity);
-        bench("custom", &mut map, capacity);
+        let prefilled = prefill(&mut map, capacity, prefill_percent, pattern);
+        bench("custom", &mut map, capacity, &prefilled);
+    } else if use_enum {
+        let mut map = EnumHashMap::with_capacity(capacity);
+        let prefilled = prefill(&mut map, capacity, prefill_percent, pattern);
+        bench("enum", &mut map, capacity, &prefilled);
     } else {
         let mut map = HashMap::with_capacity(capacity);
-        bench("std", &mut map, capacity);
+        let prefilled = prefill(&mut map, capacity, prefill_percent, pattern);
+        bench("std", &mut map, capacity, &prefilled);
     }
 }
 
+/// Order in which [`prefill`] picks its keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Pattern {
+    /// Ascending keys from 0, which pack into neighbouring buckets.
+    Sequential,
+    /// A fixed pseudo-random permutation of all 256 keys.
+    Random,
+}
+
+impl Pattern {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "sequential" => Some(Pattern::Sequential),
+            "random" => Some(Pattern::Random),
+            _ => None,
+        }
+    }
+
+    fn keys(self) -> Vec<u8> {
+        let mut keys: Vec<u8> = (0..=u8::MAX).collect();
+        if self == Pattern::Random {
+            // Fisher-Yates driven by xorshift64, so every run prefills the
+            // same keys.
+            let mut state: u64 = 0x2545_F491_4F6C_DD1D;
+            for i in (1..keys.len()).rev() {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                keys.swap(i, (state % (i as u64 + 1)) as usize);
+            }
+        }
+        keys
+    }
+}
+
+/// Inserts keys until `percent` of `capacity` slots are in use, capped at the
+/// 256 distinct `u8` keys, and returns which keys were inserted. Runs before
+/// the benchmark's clock starts so it measures a warm table.
+fn prefill<M: Map<u8, u8>>(
+    map: &mut M,
+    capacity: usize,
+    percent: usize,
+    pattern: Pattern,
+) -> [bool; 256] {
+    let mut prefilled = [false; 256];
+    let count = (capacity * percent / 100).min(prefilled.len());
+    for key in pattern.keys().into_iter().take(count) {
+        map.insert(key, key);
+        prefilled[key as usize] = true;
+    }
+    prefilled
+}
+
 trait Map<K, V> {
     fn insert(&mut self, key: K, value: V) -> Option<V>;
     fn get(&self, key: &K) -> Option<&V>;
@@ -52,12 +133,132 @@ impl Map<u8, u8> for CustomHashMap {
     }
 }
 
-fn bench<M: Map<u8, u8>>(name: &str, map: &mut M, capacity: usize) {
+/// The enum-per-slot layout `CustomHashMap` used before control bytes, kept
+/// as a baseline to benchmark the control-byte layout against. It never
+/// grows, which the benchmark's minimum capacity makes irrelevant.
+struct EnumHashMap {
+    entries: Vec<Slot>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Slot {
+    Vacant,
+    Deleted,
+    Occupied { key: u8, value: u8 },
+}
+
+impl EnumHashMap {
+    fn with_capacity(capacity: usize) -> Self {
+        Self {
+            entries: vec![Slot::Vacant; capacity],
+        }
+    }
+
+    /// Slot indices of `key`'s probe sequence, starting at its home bucket.
+    fn probe_sequence(&self, key: u8) -> impl Iterator<Item = usize> {
+        let capacity = self.entries.len();
+        (0..capacity).map(move |offset| (key as usize + offset) % capacity)
+    }
+}
+
+impl Map<u8, u8> for EnumHashMap {
+    fn insert(&mut self, key: u8, value: u8) -> Option<u8> {
+        let mut free = None;
+        for index in self.probe_sequence(key) {
+            match self.entries[index] {
+                Slot::Vacant => {
+                    free = free.or(Some(index));
+                    break;
+                }
+                Slot::Deleted => free = free.or(Some(index)),
+                Slot::Occupied {
+                    key: current_key,
+                    value: current_value,
+                } if current_key == key => {
+                    self.entries[index] = Slot::Occupied { key, value };
+                    return Some(current_value);
+                }
+                Slot::Occupied { .. } => {}
+            }
+        }
+        let index = free.expect("enum map is full");
+        self.entries[index] = Slot::Occupied { key, value };
+        None
+    }
+    fn get(&self, key: &u8) -> Option<&u8> {
+        for index in self.probe_sequence(*key) {
+            match &self.entries[index] {
+                Slot::Vacant => return None,
+                Slot::Occupied {
+                    key: current_key,
+                    value,
+                } if current_key == key => return Some(value),
+                _ => {}
+            }
+        }
+        None
+    }
+    fn remove(&mut self, key: &u8) -> Option<u8> {
+        for index in self.probe_sequence(*key) {
+            match self.entries[index] {
+                Slot::Vacant => return None,
+                Slot::Occupied {
+                    key: current_key,
+                    value,
+                } if current_key == *key => {
+                    self.entries[index] = Slot::Deleted;
+                    return Some(value);
+                }
+                _ => {}
+            }
+        }
+        None
+    }
+}
+
+/// Runs every operation against both a `CustomHashMap` and a std `HashMap`,
+/// panicking as soon as their results differ.
+struct Lockstep {
+    custom: CustomHashMap,
+    std: HashMap<u8, u8>,
+}
+
+impl Lockstep {
+    fn with_capacity(capacity: usize) -> Self {
+        Self {
+            custom: CustomHashMap::with_capacity(capacity),
+            std: HashMap::with_capacity(capacity),
+        }
+    }
+}
+
+impl Map<u8, u8> for Lockstep {
+    fn insert(&mut self, key: u8, value: u8) -> Option<u8> {
+        let custom = self.custom.insert(key, value);
+        assert_eq!(
+            custom,
+            self.std.insert(key, value),
+            "insert({key}, {value})"
+        );
+        custom
+    }
+    fn get(&self, key: &u8) -> Option<&u8> {
+        let custom = self.custom.get(*key);
+        assert_eq!(custom, self.std.get(key), "get({key})");
+        custom
+    }
+    fn remove(&mut self, key: &u8) -> Option<u8> {
+        let custom = self.custom.remove(*key);
+        assert_eq!(custom, self.std.remove(key), "remove({key})");
+        custom
+    }
+}
+
+fn bench<M: Map<u8, u8>>(name: &str, map: &mut M, capacity: usize, prefilled: &[bool; 256]) {
     println!(
         "=== {} HashMap Benchmark (capacity: {}) ===",
         name, capacity
     );
-    assert!(capacity > 1000, "Capacity must be greater than 1000");
     let mut total_ops = 0;
     let mut checksum = 0;
     let start = Instant::now();
@@ -66,8 +267,9 @@ fn bench<M: Map<u8, u8>>(name: &str, map: &mut M, capacity: usize) {
     // Initial insertions
     let t0 = Instant::now();
     let fill_size = (255_usize).min((capacity as f64 * 0.8) as usize);
-    for i in 0..fill_size {
-        assert!(map.insert(i as u8, ((i * 10) % 255) as u8).is_none());
+    for (i, &was_prefilled) in prefilled.iter().enumerate().take(fill_size) {
+        let old = map.insert(i as u8, ((i * 10) % 255) as u8);
+        assert_eq!(old.is_some(), was_prefilled);
         assert_eq!(map.get(&(i as u8)), Some(&(((i * 10) % 255) as u8)));
         total_ops += 2;
     }
@@ -175,3 +377,38 @@ fn bench<M: Map<u8, u8>>(name: &str, map: &mut M, capacity: usize) {
     println!("Final checksum: {}", checksum);
     println!("=====================================");
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn when_verify_at_small_capacity_should_agree_with_std() {
+        let mut map = Lockstep::with_capacity(64);
+        bench("verify", &mut map, 64, &[false; 256]);
+        assert_eq!(map.custom.len(), map.std.len());
+    }
+
+    #[test]
+    fn when_enum_baseline_benched_should_match_custom_checksum() {
+        let mut custom = CustomHashMap::with_capacity(1024);
+        let mut baseline = EnumHashMap::with_capacity(1024);
+        bench("custom", &mut custom, 1024, &[false; 256]);
+        bench("enum", &mut baseline, 1024, &[false; 256]);
+        for key in 0..=u8::MAX {
+            assert_eq!(Map::get(&baseline, &key), custom.get(key));
+        }
+    }
+
+    #[test]
+    fn when_prefill_50_should_half_fill_before_bench() {
+        for pattern in [Pattern::Sequential, Pattern::Random] {
+            let mut map = Lockstep::with_capacity(64);
+            let prefilled = prefill(&mut map, 64, 50, pattern);
+            assert_eq!(map.custom.len(), 32);
+            assert_eq!(prefilled.iter().filter(|&&key| key).count(), 32);
+            bench("verify", &mut map, 64, &prefilled);
+            assert_eq!(map.custom.len(), map.std.len());
+        }
+    }
+}
@@ -0,0 +1,59 @@
+use std::fmt;
+
+use crate::SHRINK_LOAD;
+
+/// Decides how a [`CustomHashMap`](crate::CustomHashMap) resizes. Every map
+/// holds one: [`DoublingPolicy`] by default, a [`GrowthFactorPolicy`] when the
+/// builder's [`growth_factor`](crate::CustomHashMapBuilder::growth_factor) or
+/// [`shrink_on_remove`](crate::CustomHashMapBuilder::shrink_on_remove) is set,
+/// or one passed to
+/// [`capacity_policy`](crate::CustomHashMapBuilder::capacity_policy).
+///
+/// Both methods return the capacity to resize to, or `None` to keep the
+/// current table. The map still rounds targets up for
+/// [`Hashing::Fibonacci`](crate::Hashing::Fibonacci) and ignores targets that
+/// could not hold its entries.
+pub trait CapacityPolicy: fmt::Debug + Send + Sync {
+    /// Called when an insert needs more room than the table has, with the
+    /// live entry count and the current capacity. Returning `None` refuses
+    /// to grow, so the insert panics as with resizing disabled.
+    fn should_grow(&self, size: usize, capacity: usize) -> Option<usize>;
+
+    /// Called after every removal with the live entry count and the current
+    /// capacity.
+    fn should_shrink(&self, size: usize, capacity: usize) -> Option<usize>;
+}
+
+/// Doubles the table when it fills up and never shrinks it. The default
+/// policy of every map.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DoublingPolicy;
+
+impl CapacityPolicy for DoublingPolicy {
+    fn should_grow(&self, _size: usize, capacity: usize) -> Option<usize> {
+        Some((capacity * 2).max(1))
+    }
+
+    fn should_shrink(&self, _size: usize, _capacity: usize) -> Option<usize> {
+        None
+    }
+}
+
+/// Multiplies the capacity by `factor` when the table fills up and, with
+/// `shrink_when_sparse`, halves it once fewer than 15% of its slots are live.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GrowthFactorPolicy {
+    pub factor: f64,
+    pub shrink_when_sparse: bool,
+}
+
+impl CapacityPolicy for GrowthFactorPolicy {
+    fn should_grow(&self, _size: usize, capacity: usize) -> Option<usize> {
+        Some((capacity as f64 * self.factor).ceil() as usize)
+    }
+
+    fn should_shrink(&self, size: usize, capacity: usize) -> Option<usize> {
+        (self.shrink_when_sparse && (size as f64) < capacity as f64 * SHRINK_LOAD)
+            .then_some(capacity / 2)
+    }
+}
@@ -0,0 +1,360 @@
+use std::fs::File;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use crate::CustomHashMap;
+
+/// Storage for entries a [`SpillingHashMap`] has evicted from memory.
+pub trait Backend {
+    /// Stores `value` for `key`, or erases the key when `value` is [`None`].
+    fn write(&mut self, key: u8, value: Option<u8>) -> io::Result<()>;
+
+    /// Returns the value stored for `key`, if any.
+    fn read(&mut self, key: u8) -> io::Result<Option<u8>>;
+}
+
+/// A [`Backend`] keeping one two-byte record per possible key in a file: a
+/// presence flag followed by the value.
+#[derive(Debug)]
+pub struct FileBackend {
+    file: File,
+}
+
+impl FileBackend {
+    /// Creates or truncates the file at `path` and sizes it for every key.
+    pub fn create(path: impl AsRef<Path>) -> io::Result<Self> {
+        let file = File::options()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+        file.set_len(2 * (u8::MAX as u64 + 1))?;
+        Ok(Self { file })
+    }
+}
+
+impl Backend for FileBackend {
+    fn write(&mut self, key: u8, value: Option<u8>) -> io::Result<()> {
+        self.file.seek(SeekFrom::Start(2 * key as u64))?;
+        self.file
+            .write_all(&[u8::from(value.is_some()), value.unwrap_or(0)])
+    }
+
+    fn read(&mut self, key: u8) -> io::Result<Option<u8>> {
+        let mut record = [0; 2];
+        self.file.seek(SeekFrom::Start(2 * key as u64))?;
+        self.file.read_exact(&mut record)?;
+        Ok((record[0] != 0).then_some(record[1]))
+    }
+}
+
+/// A map that keeps at most a fixed number of entries in memory and spills
+/// the least recently used ones to a [`Backend`], faulting them back in when
+/// they are accessed again.
+///
+/// Entries inserted with [`insert_weighted`](Self::insert_weighted) count
+/// their weight against the cap instead of 1, so the cap bounds the total
+/// weight held in memory.
+#[derive(Debug)]
+pub struct SpillingHashMap<B = FileBackend> {
+    memory: CustomHashMap,
+    backend: B,
+    max_weight: usize,
+    memory_weight: usize,
+    weights: Vec<usize>,
+    spilled: Vec<bool>,
+    spilled_len: usize,
+    last_used: Vec<u64>,
+    clock: u64,
+}
+
+impl<B: Backend> SpillingHashMap<B> {
+    /// Creates a map holding at most `max_weight` entries in memory, or
+    /// entries of at most `max_weight` total weight.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_weight` is 0.
+    pub fn with_backend(max_weight: usize, backend: B) -> Self {
+        assert!(max_weight > 0, "memory cap must be at least 1");
+        Self {
+            memory: CustomHashMap::default(),
+            backend,
+            max_weight,
+            memory_weight: 0,
+            weights: vec![0; u8::MAX as usize + 1],
+            spilled: vec![false; u8::MAX as usize + 1],
+            spilled_len: 0,
+            last_used: vec![0; u8::MAX as usize + 1],
+            clock: 0,
+        }
+    }
+
+    /// Inserts a key-value pair with weight 1, returning the old value if
+    /// the key was present in memory or spilled.
+    pub fn insert(&mut self, key: u8, value: u8) -> io::Result<Option<u8>> {
+        self.insert_weighted(key, value, 1)
+    }
+
+    /// Inserts a key-value pair that costs `weight` against the memory cap,
+    /// returning the old value if the key was present in memory or spilled.
+    ///
+    /// Least recently used entries are spilled until the total weight in
+    /// memory fits the cap again. An entry heavier than the whole cap stays
+    /// in memory on its own.
+    ///
+    /// If the key is spilled and reading or erasing it from the backend
+    /// fails, the error is returned and the map is left unchanged.
+    pub fn insert_weighted(&mut self, key: u8, value: u8, weight: usize) -> io::Result<Option<u8>> {
+        let spilled = self.unspill(key)?;
+        self.touch(key);
+        let old = match self.memory.insert(key, value) {
+            Some(old) => {
+                self.memory_weight -= self.weights[key as usize];
+                Some(old)
+            }
+            None => spilled,
+        };
+        self.weights[key as usize] = weight;
+        self.memory_weight += weight;
+        // The new entry is the most recently used, so it is the last victim.
+        self.evict_to_cap()?;
+        Ok(old)
+    }
+
+    /// Returns a copy of the value corresponding to the key, faulting it back
+    /// into memory if it was spilled.
+    pub fn get(&mut self, key: u8) -> io::Result<Option<u8>> {
+        if self.memory.get(key).is_none() {
+            let Some(value) = self.unspill(key)? else {
+                return Ok(None);
+            };
+            self.memory.insert(key, value);
+            self.memory_weight += self.weights[key as usize];
+        }
+        self.touch(key);
+        self.evict_to_cap()?;
+        Ok(self.memory.get(key).copied())
+    }
+
+    /// Removes a key from the map, returning its value if it was present in
+    /// memory or spilled.
+    pub fn remove(&mut self, key: u8) -> io::Result<Option<u8>> {
+        match self.memory.remove(key) {
+            Some(value) => {
+                self.memory_weight -= self.weights[key as usize];
+                Ok(Some(value))
+            }
+            None => self.unspill(key),
+        }
+    }
+
+    /// Returns the number of entries, in memory and spilled.
+    pub fn len(&self) -> usize {
+        self.memory.len() + self.spilled_len
+    }
+
+    /// Returns `true` if the map contains no elements.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the number of entries currently held in memory.
+    pub fn memory_len(&self) -> usize {
+        self.memory.len()
+    }
+
+    /// Returns the total weight of the entries currently held in memory.
+    pub fn memory_weight(&self) -> usize {
+        self.memory_weight
+    }
+
+    fn touch(&mut self, key: u8) {
+        self.clock += 1;
+        self.last_used[key as usize] = self.clock;
+    }
+
+    /// Erases a spilled `key` from the backend and returns its value.
+    fn unspill(&mut self, key: u8) -> io::Result<Option<u8>> {
+        if !self.spilled[key as usize] {
+            return Ok(None);
+        }
+        let value = self.backend.read(key)?;
+        self.backend.write(key, None)?;
+        self.spilled[key as usize] = false;
+        self.spilled_len -= 1;
+        Ok(value)
+    }
+
+    /// Spills least recently used entries until memory is within the cap,
+    /// keeping at least one entry. A victim only leaves memory once the
+    /// backend has stored it, so a failed write loses nothing.
+    fn evict_to_cap(&mut self) -> io::Result<()> {
+        while self.memory_weight > self.max_weight && self.memory.len() > 1 {
+            let (victim, value) = self
+                .memory
+                .live_entries()
+                .min_by_key(|&(key, _)| self.last_used[key as usize])
+                .expect("map over its cap is not empty");
+            self.backend.write(victim, Some(value))?;
+            self.memory.remove(victim);
+            self.memory_weight -= self.weights[victim as usize];
+            self.spilled[victim as usize] = true;
+            self.spilled_len += 1;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    #[derive(Default)]
+    struct MockBackend {
+        entries: HashMap<u8, u8>,
+        writes: usize,
+        failing: bool,
+    }
+
+    impl MockBackend {
+        fn check(&self) -> io::Result<()> {
+            if self.failing {
+                return Err(io::Error::other("backend unavailable"));
+            }
+            Ok(())
+        }
+    }
+
+    impl Backend for &mut MockBackend {
+        fn write(&mut self, key: u8, value: Option<u8>) -> io::Result<()> {
+            self.check()?;
+            self.writes += 1;
+            match value {
+                Some(value) => self.entries.insert(key, value),
+                None => self.entries.remove(&key),
+            };
+            Ok(())
+        }
+
+        fn read(&mut self, key: u8) -> io::Result<Option<u8>> {
+            self.check()?;
+            Ok(self.entries.get(&key).copied())
+        }
+    }
+
+    #[test]
+    fn when_over_memory_cap_should_spill_least_recently_used() {
+        let mut backend = MockBackend::default();
+        let mut map = SpillingHashMap::with_backend(2, &mut backend);
+        map.insert(1, 10).unwrap();
+        map.insert(2, 20).unwrap();
+        map.get(1).unwrap();
+        map.insert(3, 30).unwrap(); // spills 2, the least recently used
+        assert_eq!(map.len(), 3);
+        assert_eq!(map.memory_len(), 2);
+        assert_eq!(map.backend.entries, HashMap::from([(2, 20)]));
+
+        assert_eq!(map.get(2).unwrap(), Some(20)); // faults 2 in, spills 1
+        assert_eq!(map.backend.entries, HashMap::from([(1, 10)]));
+        assert_eq!(map.insert(1, 11).unwrap(), Some(10));
+        assert_eq!(map.remove(3).unwrap(), Some(30));
+        assert_eq!(map.get(9).unwrap(), None);
+        assert_eq!(map.len(), 2);
+    }
+
+    #[test]
+    fn when_spilled_entry_removed_should_erase_it_from_backend() {
+        let mut backend = MockBackend::default();
+        let mut map = SpillingHashMap::with_backend(1, &mut backend);
+        map.insert(1, 10).unwrap();
+        map.insert(2, 20).unwrap();
+        assert_eq!(map.remove(1).unwrap(), Some(10));
+        assert_eq!(map.remove(1).unwrap(), None);
+        assert_eq!(map.len(), 1);
+        assert!(backend.entries.is_empty());
+        assert_eq!(backend.writes, 2);
+    }
+
+    #[test]
+    fn when_weighted_entries_exceed_budget_should_spill_until_they_fit() {
+        let mut backend = MockBackend::default();
+        let mut map = SpillingHashMap::with_backend(10, &mut backend);
+        map.insert_weighted(1, 10, 4).unwrap();
+        map.insert_weighted(2, 20, 4).unwrap();
+        map.insert(3, 30).unwrap();
+        assert_eq!(map.memory_weight(), 9);
+        map.insert_weighted(4, 40, 3).unwrap(); // spills 1
+        assert_eq!(map.memory_weight(), 8);
+        assert_eq!(map.memory_len(), 3);
+        map.insert_weighted(5, 50, 7).unwrap(); // spills 2 and 3
+        assert_eq!(map.memory_weight(), 10);
+        assert_eq!(map.memory_len(), 2);
+        assert_eq!(map.get(1).unwrap(), Some(10)); // faults 1 in, spills 4 and 5
+        assert_eq!(map.memory_weight(), 4);
+        map.insert_weighted(6, 60, 12).unwrap(); // heavier than the budget
+        assert_eq!(map.memory_weight(), 12);
+        assert_eq!(map.memory_len(), 1);
+        assert_eq!(map.remove(6).unwrap(), Some(60));
+        assert_eq!(map.memory_weight(), 0);
+        assert_eq!(map.len(), 5);
+        assert_eq!(
+            map.backend.entries,
+            HashMap::from([(1, 10), (2, 20), (3, 30), (4, 40), (5, 50)])
+        );
+    }
+
+    #[test]
+    fn when_spill_write_fails_should_keep_victim_in_memory() {
+        let mut backend = MockBackend::default();
+        let mut map = SpillingHashMap::with_backend(1, &mut backend);
+        map.insert(1, 10).unwrap();
+        map.backend.failing = true;
+        assert!(map.insert(2, 20).is_err());
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.memory_weight(), 2);
+        assert!(!map.spilled[1]);
+
+        map.backend.failing = false;
+        assert_eq!(map.get(1).unwrap(), Some(10)); // spills 2 now
+        assert_eq!(map.get(2).unwrap(), Some(20));
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.memory_weight(), 1);
+    }
+
+    #[test]
+    fn when_unspill_fails_on_insert_should_leave_map_unchanged() {
+        let mut backend = MockBackend::default();
+        let mut map = SpillingHashMap::with_backend(1, &mut backend);
+        map.insert(1, 10).unwrap();
+        map.insert(2, 20).unwrap(); // spills 1
+        map.backend.failing = true;
+        assert!(map.insert(1, 11).is_err());
+        assert_eq!(map.len(), 2);
+        assert_eq!(map.memory_len(), 1);
+        assert_eq!(map.memory_weight(), 1);
+
+        map.backend.failing = false;
+        assert_eq!(map.insert(1, 11).unwrap(), Some(10));
+        assert_eq!(map.get(1).unwrap(), Some(11));
+        assert_eq!(map.len(), 2);
+    }
+
+    #[test]
+    fn when_file_backed_should_round_trip_spilled_entries() {
+        let path = std::env::temp_dir().join(format!("spill-{}.bin", std::process::id()));
+        let mut map = SpillingHashMap::with_backend(4, FileBackend::create(&path).unwrap());
+        for key in 0..32 {
+            map.insert(key, key * 2).unwrap();
+        }
+        assert_eq!(map.memory_len(), 4);
+        for key in 0..32 {
+            assert_eq!(map.get(key).unwrap(), Some(key * 2));
+        }
+        assert_eq!(map.len(), 32);
+        std::fs::remove_file(path).unwrap();
+    }
+}
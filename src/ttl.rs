@@ -0,0 +1,220 @@
+use std::time::{Duration, Instant};
+
+use crate::{is_full, CustomHashMap, EMPTY};
+
+/// A source of the current time for [`TtlHashMap`].
+pub trait Clock {
+    fn now(&self) -> Instant;
+}
+
+/// The system's monotonic clock.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+/// A cache whose entries expire a fixed time after they were inserted.
+///
+/// Expiry is lazy: a lookup, insert or removal removes every expired entry
+/// on the probe chain it walks, and [`purge_expired`](Self::purge_expired)
+/// sweeps the whole table. Until then an expired entry keeps its slot and
+/// counts towards [`len`](Self::len).
+#[derive(Debug)]
+pub struct TtlHashMap<C = SystemClock> {
+    map: CustomHashMap,
+    inserted_at: Vec<Option<Instant>>,
+    expire_after: Duration,
+    clock: C,
+}
+
+impl TtlHashMap<SystemClock> {
+    /// Creates a cache whose entries expire `expire_after` after insertion.
+    pub fn new(expire_after: Duration) -> Self {
+        Self::with_clock(expire_after, SystemClock)
+    }
+}
+
+impl<C: Clock> TtlHashMap<C> {
+    /// Creates a cache that reads the time from `clock`.
+    pub fn with_clock(expire_after: Duration, clock: C) -> Self {
+        Self {
+            map: CustomHashMap::default(),
+            inserted_at: vec![None; u8::MAX as usize + 1],
+            expire_after,
+            clock,
+        }
+    }
+
+    /// Inserts a key-value pair stamped with the current time, restarting the
+    /// key's time to live. Returns the old value if the key was present and
+    /// not yet expired.
+    pub fn insert_with_ttl(&mut self, key: u8, value: u8) -> Option<u8> {
+        self.expire(key);
+        self.inserted_at[key as usize] = Some(self.clock.now());
+        self.map.insert(key, value)
+    }
+
+    /// Returns a reference to the value corresponding to the key, or [`None`]
+    /// if it is absent or expired. An expired entry is removed.
+    pub fn get(&mut self, key: u8) -> Option<&u8> {
+        self.expire(key);
+        self.map.get(key)
+    }
+
+    /// Removes a key from the cache, returning its value if it was present
+    /// and not yet expired.
+    pub fn remove(&mut self, key: u8) -> Option<u8> {
+        self.expire(key);
+        self.inserted_at[key as usize] = None;
+        self.map.remove(key)
+    }
+
+    /// Removes every expired entry and returns how many were removed.
+    pub fn purge_expired(&mut self) -> usize {
+        let now = self.clock.now();
+        let mut removed = 0;
+        for key in 0..=u8::MAX {
+            if self.is_expired(key, now) {
+                self.inserted_at[key as usize] = None;
+                self.map.remove(key);
+                removed += 1;
+            }
+        }
+        removed
+    }
+
+    /// Returns the number of entries, including expired ones not yet removed.
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    /// Returns `true` if the cache holds no entries, expired or not.
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    /// Removes every entry on the probe chain of `key`, `key` included, that
+    /// has outlived the time to live.
+    fn expire(&mut self, key: u8) {
+        let map = &self.map;
+        if map.capacity == 0 {
+            return;
+        }
+        let (home, now) = (map.hash(key), self.clock.now());
+        let mut expired = Vec::new();
+        for offset in 0..map.capacity {
+            let index = (home + offset) % map.capacity;
+            if map.ctrl[index] == EMPTY {
+                break;
+            }
+            let current = map.slots[index].0;
+            if is_full(map.ctrl[index]) && self.is_expired(current, now) {
+                expired.push(current);
+            }
+        }
+        for key in expired {
+            self.inserted_at[key as usize] = None;
+            self.map.remove(key);
+        }
+    }
+
+    fn is_expired(&self, key: u8, now: Instant) -> bool {
+        self.inserted_at[key as usize]
+            .is_some_and(|inserted_at| now.duration_since(inserted_at) > self.expire_after)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::*;
+
+    /// A clock that only moves when told to.
+    struct MockClock {
+        start: Instant,
+        elapsed: Cell<Duration>,
+    }
+
+    impl MockClock {
+        fn new() -> Self {
+            Self {
+                start: Instant::now(),
+                elapsed: Cell::new(Duration::ZERO),
+            }
+        }
+
+        fn advance(&self, by: Duration) {
+            self.elapsed.set(self.elapsed.get() + by);
+        }
+    }
+
+    impl Clock for &MockClock {
+        fn now(&self) -> Instant {
+            self.start + self.elapsed.get()
+        }
+    }
+
+    #[test]
+    fn when_clock_passes_ttl_should_expire_entry() {
+        let clock = MockClock::new();
+        let mut cache = TtlHashMap::with_clock(Duration::from_secs(10), &clock);
+        cache.insert_with_ttl(1, 10);
+        clock.advance(Duration::from_secs(6));
+        cache.insert_with_ttl(2, 20);
+        assert_eq!(cache.get(1), Some(&10));
+
+        clock.advance(Duration::from_secs(6));
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get(1), None);
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.get(2), Some(&20));
+    }
+
+    #[test]
+    fn when_lookup_walks_past_expired_entries_should_remove_them() {
+        let clock = MockClock::new();
+        let mut cache = TtlHashMap::with_clock(Duration::from_secs(10), &clock);
+        cache.insert_with_ttl(1, 10); // hash: 1
+        cache.insert_with_ttl(17, 170); // hash: 1, probed to 2
+        cache.insert_with_ttl(4, 40); // hash: 4, past the empty slot 3
+        clock.advance(Duration::from_secs(11));
+        assert_eq!(cache.get(9), None); // hash: 1
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.map.get(17), None);
+    }
+
+    #[test]
+    fn when_purged_should_remove_every_expired_entry() {
+        let clock = MockClock::new();
+        let mut cache = TtlHashMap::with_clock(Duration::from_secs(10), &clock);
+        for key in 0..101 {
+            cache.insert_with_ttl(key, key);
+        }
+        clock.advance(Duration::from_secs(5));
+        cache.insert_with_ttl(200, 1);
+        clock.advance(Duration::from_secs(6));
+        assert_eq!(cache.purge_expired(), 101);
+        assert_eq!(cache.len(), 1);
+        assert_eq!(cache.get(200), Some(&1));
+    }
+
+    #[test]
+    fn when_reinserted_should_restart_ttl() {
+        let clock = MockClock::new();
+        let mut cache = TtlHashMap::with_clock(Duration::from_secs(10), &clock);
+        cache.insert_with_ttl(1, 10);
+        clock.advance(Duration::from_secs(8));
+        assert_eq!(cache.insert_with_ttl(1, 11), Some(10));
+        clock.advance(Duration::from_secs(8));
+        assert_eq!(cache.get(1), Some(&11));
+        clock.advance(Duration::from_secs(8));
+        assert_eq!(cache.insert_with_ttl(1, 12), None); // expired before the insert
+        assert_eq!(cache.remove(1), Some(12));
+        assert!(cache.is_empty());
+    }
+}
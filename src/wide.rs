@@ -0,0 +1,245 @@
+//! Maps with wider integer keys.
+//!
+//! `key % capacity` only spreads `u8` keys well because the key space is as
+//! small as the table. The maps here use Fibonacci hashing instead, keep their
+//! capacity a power of two and share the probing code with
+//! [`CustomHashMap`](crate::CustomHashMap). Values are still `u8`.
+
+use crate::{free_slot, h2, is_full, probe, DEFAULT_CAPACITY, EMPTY};
+
+macro_rules! wide_hash_map {
+    ($(#[$meta:meta])* $name:ident, $key:ty) => {
+        $(#[$meta])*
+        #[derive(Debug, Clone)]
+        pub struct $name {
+            ctrl: Vec<u8>,
+            slots: Vec<($key, u8)>,
+            size: usize,
+            deleted: usize,
+        }
+
+        impl Default for $name {
+            fn default() -> Self {
+                Self::with_capacity(DEFAULT_CAPACITY)
+            }
+        }
+
+        impl $name {
+            /// Creates a map with at least `capacity` slots, rounded up to a
+            /// power of two.
+            pub fn with_capacity(capacity: usize) -> Self {
+                let capacity = capacity.next_power_of_two();
+                Self {
+                    ctrl: vec![EMPTY; capacity],
+                    slots: vec![(0, 0); capacity],
+                    size: 0,
+                    deleted: 0,
+                }
+            }
+
+            /// Inserts a key-value pair into the map, returning the old value
+            /// if the key was present. Grows the table once more than 3/4 of
+            /// its slots would be live, and rehashes it at the same capacity
+            /// once live entries and tombstones together would pass 3/4.
+            pub fn insert(&mut self, key: $key, value: u8) -> Option<u8> {
+                let mut index = self.probe(key, true);
+                if !is_full(self.ctrl[index]) {
+                    let max_load = self.capacity() * 3 / 4;
+                    if self.size >= max_load {
+                        self.resize(self.capacity() * 2);
+                        index = self.probe(key, true);
+                    } else if self.ctrl[index] == EMPTY && self.size + self.deleted >= max_load {
+                        self.resize(self.capacity());
+                        index = self.probe(key, true);
+                    }
+                }
+                if is_full(self.ctrl[index]) {
+                    return Some(std::mem::replace(&mut self.slots[index].1, value));
+                }
+                if self.ctrl[index] != EMPTY {
+                    self.deleted -= 1;
+                }
+                self.ctrl[index] = tag(key);
+                self.slots[index] = (key, value);
+                self.size += 1;
+                None
+            }
+
+            /// Returns a reference to the value corresponding to the key.
+            pub fn get(&self, key: $key) -> Option<&u8> {
+                self.find(key).map(|index| &self.slots[index].1)
+            }
+
+            /// Removes a key from the map, returning the value at the key if
+            /// the key was previously in the map.
+            pub fn remove(&mut self, key: $key) -> Option<u8> {
+                let index = self.find(key)?;
+                free_slot(&mut self.ctrl, index, &mut self.deleted);
+                self.size -= 1;
+                Some(self.slots[index].1)
+            }
+
+            /// Returns the number of elements in the map.
+            pub fn len(&self) -> usize {
+                self.size
+            }
+
+            /// Returns `true` if the map contains no elements.
+            pub fn is_empty(&self) -> bool {
+                self.size == 0
+            }
+
+            /// Returns the number of slots in the table.
+            pub fn capacity(&self) -> usize {
+                self.ctrl.len()
+            }
+
+            /// The top log2(capacity) bits of the key times the 64-bit
+            /// golden-ratio constant.
+            fn hash(&self, key: $key) -> usize {
+                let shift = u64::BITS - self.capacity().trailing_zeros();
+                (key as u64)
+                    .wrapping_mul(0x9E37_79B9_7F4A_7C15)
+                    .checked_shr(shift)
+                    .unwrap_or(0) as usize
+            }
+
+            fn resize(&mut self, new_capacity: usize) {
+                let old = std::mem::replace(self, Self::with_capacity(new_capacity));
+                for (&ctrl, &(key, value)) in old.ctrl.iter().zip(&old.slots) {
+                    if is_full(ctrl) {
+                        let index = self.probe(key, true);
+                        self.ctrl[index] = tag(key);
+                        self.slots[index] = (key, value);
+                        self.size += 1;
+                    }
+                }
+            }
+
+            fn find(&self, key: $key) -> Option<usize> {
+                self.probe_inner(key, false).filter(|&index| is_full(self.ctrl[index]))
+            }
+
+            /// Returns the slot holding `key` or the free slot it goes in.
+            /// Keeping live entries and tombstones under 3/4 load leaves an
+            /// `EMPTY` slot on every probe chain.
+            fn probe(&self, key: $key, reuse_deleted: bool) -> usize {
+                self.probe_inner(key, reuse_deleted)
+                    .expect("table below max load has a free slot")
+            }
+
+            fn probe_inner(&self, key: $key, reuse_deleted: bool) -> Option<usize> {
+                probe(
+                    &self.ctrl,
+                    &self.slots,
+                    self.hash(key),
+                    key,
+                    tag(key),
+                    reuse_deleted,
+                )
+            }
+        }
+    };
+}
+
+/// Control byte of a slot holding `key`: the low seven bits of the key, as
+/// for `u8` keys.
+fn tag(key: impl Into<u64>) -> u8 {
+    h2(key.into() as u8)
+}
+
+wide_hash_map!(
+    /// A linear-probing map from `u16` keys to `u8` values.
+    CustomHashMapU16,
+    u16
+);
+wide_hash_map!(
+    /// A linear-probing map from `u32` keys to `u8` values.
+    CustomHashMapU32,
+    u32
+);
+wide_hash_map!(
+    /// A linear-probing map from `u64` keys to `u8` values.
+    CustomHashMapU64,
+    u64
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! wide_hash_map_tests {
+        ($module:ident, $name:ident, $key:ty) => {
+            mod $module {
+                use super::*;
+
+                #[test]
+                fn when_insert_get_remove_should_behave_like_u8_map() {
+                    let mut map = $name::default();
+                    let big = <$key>::MAX - 7;
+                    assert_eq!(map.insert(big, 10), None);
+                    assert_eq!(map.insert(300, 30), None);
+                    assert_eq!(map.insert(big, 11), Some(10));
+                    assert_eq!(map.get(big), Some(&11));
+                    assert_eq!(map.remove(300), Some(30));
+                    assert_eq!(map.get(300), None);
+                    assert_eq!(map.remove(300), None);
+                    assert_eq!(map.len(), 1);
+                }
+
+                #[test]
+                fn when_keys_share_home_should_probe_past_each_other() {
+                    let mut map = $name::with_capacity(16);
+                    let home = map.hash(1000);
+                    let colliding: Vec<$key> = (1000..)
+                        .filter(|&key| map.hash(key) == home)
+                        .take(3)
+                        .collect();
+                    for (value, &key) in colliding.iter().enumerate() {
+                        map.insert(key, value as u8);
+                    }
+                    assert_eq!(map.capacity(), 16);
+                    map.remove(colliding[1]);
+                    assert_eq!(map.get(colliding[0]), Some(&0));
+                    assert_eq!(map.get(colliding[2]), Some(&2));
+                    assert_eq!(map.len(), 2);
+                }
+
+                #[test]
+                fn when_keys_churn_should_reclaim_tombstones_without_growing() {
+                    let mut map = $name::with_capacity(64);
+                    for round in 0..2000 {
+                        let key = round * 31;
+                        map.insert(key, round as u8);
+                        if round >= 40 {
+                            assert_eq!(map.remove((round - 40) * 31), Some((round - 40) as u8));
+                        }
+                        assert!(map.size + map.deleted <= 48);
+                    }
+                    assert_eq!(map.capacity(), 64);
+                    assert_eq!(map.len(), 40);
+                    for round in 1960..2000 {
+                        assert_eq!(map.get(round * 31), Some(&(round as u8)));
+                    }
+                }
+
+                #[test]
+                fn when_filled_past_load_factor_should_grow() {
+                    let mut map = $name::default();
+                    for key in 0..1000 {
+                        map.insert(key * 61, key as u8);
+                    }
+                    assert_eq!(map.len(), 1000);
+                    assert_eq!(map.capacity(), 2048);
+                    for key in 0..1000 {
+                        assert_eq!(map.get(key * 61), Some(&(key as u8)));
+                    }
+                }
+            }
+        };
+    }
+
+    wide_hash_map_tests!(u16_keys, CustomHashMapU16, u16);
+    wide_hash_map_tests!(u32_keys, CustomHashMapU32, u32);
+    wide_hash_map_tests!(u64_keys, CustomHashMapU64, u64);
+}